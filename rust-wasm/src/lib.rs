@@ -1,7 +1,9 @@
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::types::{Types, TypesBuilder};
 use std::cell::UnsafeCell;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU32, Ordering};
 
 // NEXT_ID increments monotonically per instance. At u32::MAX wrap, `id as i32`
@@ -14,15 +16,131 @@ static NEXT_ID: AtomicU32 = AtomicU32::new(1);
 struct SingleThreaded<T>(UnsafeCell<T>);
 unsafe impl<T> Sync for SingleThreaded<T> {}
 
-static MATCHERS: SingleThreaded<Option<HashMap<u32, Gitignore>>> =
+/// A compiled matcher stored behind a handle. `Single` is the plain
+/// one-`.gitignore` case; `Layered` models a directory tree where deeper
+/// `.gitignore` files take precedence over shallower ones; `Overrides` is an
+/// `--include`/`--exclude`-style glob set rather than a gitignore; `Composite`
+/// is `Layered`'s incrementally-built sibling, backed by a trie for
+/// longest-prefix-origin lookup instead of a sorted `Vec`.
+enum Matcher {
+    Single(Gitignore),
+    /// Sorted deepest-base-first so matching can stop at the first
+    /// decisive layer (closest-ancestor-wins, as in git).
+    Layered(Vec<(PathBuf, Gitignore)>),
+    Overrides(Override),
+    Composite(Composite),
+    /// A "filter to these language types" matcher, e.g. `rust`, `js`.
+    Types(Types),
+}
+
+/// Custom `(name, glob)` file type definitions registered via
+/// `register_custom_type`, applied on top of the built-in defaults the next
+/// time `build_types_matcher` is called.
+static CUSTOM_TYPES: SingleThreaded<Option<Vec<(String, String)>>> =
+    SingleThreaded(UnsafeCell::new(None));
+
+fn custom_types() -> &'static mut Vec<(String, String)> {
+    // SAFETY: single-threaded WASM; no concurrent access possible.
+    let c = unsafe { &mut *CUSTOM_TYPES.0.get() };
+    c.get_or_insert_with(Vec::new)
+}
+
+/// A per-path-component trie mapping directory origins to the `Gitignore`
+/// sourced from that directory. Used by the composite matcher to find, in
+/// O(path depth), the chain of sources applicable to a path ordered from
+/// the most specific (deepest) origin to the least.
+#[derive(Default)]
+struct Composite {
+    root: TrieNode,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: HashMap<String, TrieNode>,
+    source: Option<(PathBuf, Gitignore)>,
+}
+
+impl Composite {
+    fn new() -> Composite {
+        Composite::default()
+    }
+
+    /// Register a `.gitignore` source rooted at `origin`, inserting trie
+    /// nodes for any path components not already present.
+    fn add_source(&mut self, origin: PathBuf, gitignore: Gitignore) {
+        let mut node = &mut self.root;
+        for component in origin.components() {
+            let key = component.as_os_str().to_string_lossy().into_owned();
+            node = node.children.entry(key).or_default();
+        }
+        node.source = Some((origin, gitignore));
+    }
+
+    /// Collect the chain of sources whose origin is an ancestor of `path`,
+    /// ordered deepest-origin-first.
+    fn sources_for(&self, path: &Path) -> Vec<&(PathBuf, Gitignore)> {
+        let mut node = &self.root;
+        let mut chain = Vec::new();
+        if let Some(source) = &node.source {
+            chain.push(source);
+        }
+        for component in path.components() {
+            let key = component.as_os_str().to_string_lossy();
+            match node.children.get(key.as_ref()) {
+                Some(next) => node = next,
+                None => break,
+            }
+            if let Some(source) = &node.source {
+                chain.push(source);
+            }
+        }
+        chain.reverse();
+        chain
+    }
+}
+
+static MATCHERS: SingleThreaded<Option<HashMap<u32, Matcher>>> =
     SingleThreaded(UnsafeCell::new(None));
 
-fn matchers() -> &'static mut HashMap<u32, Gitignore> {
+fn matchers() -> &'static mut HashMap<u32, Matcher> {
     // SAFETY: single-threaded WASM; no concurrent access possible.
     let m = unsafe { &mut *MATCHERS.0.get() };
     m.get_or_insert_with(HashMap::new)
 }
 
+/// Filesystem access needed to auto-discover ignore files by walking up to
+/// a VCS root (`build_matcher_from_path`) and to recursively list a
+/// directory tree (`walk_and_filter`). `wasm32-unknown-unknown` has no
+/// filesystem syscalls of its own, so on that target these are imports the
+/// embedding host must provide; `host_exists`/`read_host_file`/
+/// `list_host_dir` below are the typed wrappers everything else in this
+/// module calls, and on every other target — i.e. this crate's own test
+/// suite — those wrappers go straight to `std::fs` instead, so the
+/// walk/traversal logic has a real filesystem to run against without
+/// needing a host or any pointer marshaling at all.
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    /// Returns 1 if `path` exists (file or directory), 0 otherwise. Used to
+    /// recognize a VCS root by the presence of `.git`, which may itself be
+    /// a directory or, for worktrees and submodules, a file.
+    fn host_path_exists(path_ptr: i32, path_len: i32) -> i32;
+
+    /// Reads the full contents of the file at `path`. On success, allocates
+    /// the result via this module's own `alloc` and writes its `(ptr, len)`
+    /// into the 8-byte slot at `out_info_ptr`, returning 1; the caller owns
+    /// that buffer and must `dealloc` it. Returns 0 if `path` doesn't exist
+    /// or can't be read as a file.
+    fn host_read_file(path_ptr: i32, path_len: i32, out_info_ptr: i32) -> i32;
+
+    /// Lists the immediate entries of the directory at `path` as a buffer of
+    /// `i32 name_len | name_len bytes (UTF-8 name) | i32 is_dir` records,
+    /// allocated via this module's own `alloc`. Writes `(ptr, len)` into the
+    /// 8-byte slot at `out_info_ptr` and returns the entry count (>= 0), or
+    /// -1 if `path` isn't a readable directory.
+    fn host_list_dir(path_ptr: i32, path_len: i32, out_info_ptr: i32) -> i32;
+}
+
+
 /// Match result for a single path.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MatchResult {
@@ -31,6 +149,26 @@ pub enum MatchResult {
     Whitelist = 2,
 }
 
+/// The full diagnostic behind a `MatchResult`: which glob decided it (when
+/// the underlying matcher's API exposes one) and, for multi-source
+/// matchers, which origin it came from.
+struct Explanation {
+    result: MatchResult,
+    /// The glob text that matched, or the matched type name for a `Types`
+    /// matcher. `None` when no glob is available, including the override
+    /// "unmatched but still ignored" case.
+    glob: Option<String>,
+    /// The origin (e.g. directory) the deciding source came from, for
+    /// `Layered`/`Composite` matchers. `None` for single-source matchers.
+    source: Option<String>,
+}
+
+impl Explanation {
+    fn none() -> Explanation {
+        Explanation { result: MatchResult::None, glob: None, source: None }
+    }
+}
+
 /// Build a `Gitignore` from a newline-separated pattern byte slice.
 /// Lines that fail to parse or are not valid UTF-8 are silently skipped.
 fn build_matcher(patterns: &[u8]) -> Result<Gitignore, ignore::Error> {
@@ -43,6 +181,19 @@ fn build_matcher(patterns: &[u8]) -> Result<Gitignore, ignore::Error> {
     builder.build()
 }
 
+/// Build an `Override` (include/exclude) matcher from a newline-separated
+/// glob byte slice. Lines that fail to parse or are not valid UTF-8 are
+/// silently skipped.
+fn build_overrides(globs: &[u8]) -> Result<Override, ignore::Error> {
+    let mut builder = OverrideBuilder::new(Path::new("/"));
+    for line_bytes in globs.split(|&b| b == b'\n') {
+        if let Ok(line) = std::str::from_utf8(line_bytes) {
+            let _ = builder.add(line);
+        }
+    }
+    builder.build()
+}
+
 /// Match a path against a compiled gitignore matcher.
 fn match_path(gitignore: &Gitignore, path: &str, is_dir: bool) -> MatchResult {
     match gitignore.matched_path_or_any_parents(Path::new(path), is_dir) {
@@ -52,9 +203,218 @@ fn match_path(gitignore: &Gitignore, path: &str, is_dir: bool) -> MatchResult {
     }
 }
 
+/// Match a path against a stack of per-directory gitignore layers.
+///
+/// `layers` must already be sorted deepest-base-first. The first layer whose
+/// base is an ancestor of `path` and that returns a decisive `Ignore` or
+/// `Whitelist` wins; a `None` from a deeper layer falls through to the next
+/// shallower one, mirroring git's closest-`.gitignore`-wins precedence.
+fn match_path_layered(layers: &[(PathBuf, Gitignore)], path: &str, is_dir: bool) -> MatchResult {
+    let full_path = Path::new(path);
+    for (base, gitignore) in layers {
+        if !full_path.starts_with(base) {
+            continue;
+        }
+        let relative = full_path.strip_prefix(base).unwrap_or(full_path);
+        match gitignore.matched_path_or_any_parents(relative, is_dir) {
+            ignore::Match::None => continue,
+            ignore::Match::Ignore(_) => return MatchResult::Ignore,
+            ignore::Match::Whitelist(_) => return MatchResult::Whitelist,
+        }
+    }
+    MatchResult::None
+}
+
+/// Match a path against a composite matcher's trie of per-directory sources.
+/// Semantics mirror `match_path_layered`: the deepest applicable origin wins,
+/// and a `None` from a deeper source falls through to the next shallower one.
+fn match_path_composite(composite: &Composite, path: &str, is_dir: bool) -> MatchResult {
+    let full_path = Path::new(path);
+    for (origin, gitignore) in composite.sources_for(full_path) {
+        let relative = full_path.strip_prefix(origin).unwrap_or(full_path);
+        match gitignore.matched_path_or_any_parents(relative, is_dir) {
+            ignore::Match::None => continue,
+            ignore::Match::Ignore(_) => return MatchResult::Ignore,
+            ignore::Match::Whitelist(_) => return MatchResult::Whitelist,
+        }
+    }
+    MatchResult::None
+}
+
+/// Explain a match against a plain single-source gitignore matcher.
+fn explain_single(gitignore: &Gitignore, path: &str, is_dir: bool) -> Explanation {
+    match gitignore.matched_path_or_any_parents(Path::new(path), is_dir) {
+        ignore::Match::None => Explanation::none(),
+        ignore::Match::Ignore(glob) => Explanation {
+            result: MatchResult::Ignore,
+            glob: Some(glob.original().to_string()),
+            source: None,
+        },
+        ignore::Match::Whitelist(glob) => Explanation {
+            result: MatchResult::Whitelist,
+            glob: Some(glob.original().to_string()),
+            source: None,
+        },
+    }
+}
+
+/// Explain a match against a layered matcher, reporting which base directory
+/// the deciding layer was rooted at. Mirrors `match_path_layered`'s
+/// deepest-base-first, fall-through-on-`None` traversal.
+fn explain_layered(layers: &[(PathBuf, Gitignore)], path: &str, is_dir: bool) -> Explanation {
+    let full_path = Path::new(path);
+    for (base, gitignore) in layers {
+        if !full_path.starts_with(base) {
+            continue;
+        }
+        let relative = full_path.strip_prefix(base).unwrap_or(full_path);
+        let source = Some(base.display().to_string());
+        match gitignore.matched_path_or_any_parents(relative, is_dir) {
+            ignore::Match::None => continue,
+            ignore::Match::Ignore(glob) => {
+                return Explanation {
+                    result: MatchResult::Ignore,
+                    glob: Some(glob.original().to_string()),
+                    source,
+                };
+            }
+            ignore::Match::Whitelist(glob) => {
+                return Explanation {
+                    result: MatchResult::Whitelist,
+                    glob: Some(glob.original().to_string()),
+                    source,
+                };
+            }
+        }
+    }
+    Explanation::none()
+}
+
+/// Explain a match against a composite matcher, reporting the origin of the
+/// deciding source. Mirrors `match_path_composite`'s traversal order.
+fn explain_composite(composite: &Composite, path: &str, is_dir: bool) -> Explanation {
+    let full_path = Path::new(path);
+    for (origin, gitignore) in composite.sources_for(full_path) {
+        let relative = full_path.strip_prefix(origin).unwrap_or(full_path);
+        let source = Some(origin.display().to_string());
+        match gitignore.matched_path_or_any_parents(relative, is_dir) {
+            ignore::Match::None => continue,
+            ignore::Match::Ignore(glob) => {
+                return Explanation {
+                    result: MatchResult::Ignore,
+                    glob: Some(glob.original().to_string()),
+                    source,
+                };
+            }
+            ignore::Match::Whitelist(glob) => {
+                return Explanation {
+                    result: MatchResult::Whitelist,
+                    glob: Some(glob.original().to_string()),
+                    source,
+                };
+            }
+        }
+    }
+    Explanation::none()
+}
+
+/// Explain a match against an override matcher. `ignore::overrides::Glob`
+/// exposes no public accessors (not even the unmatched-ignore/concrete-match
+/// distinction), so only the result code is reportable here.
+fn explain_overrides(overrides: &Override, path: &str, is_dir: bool) -> Explanation {
+    Explanation {
+        result: match_path_override(overrides, path, is_dir),
+        glob: None,
+        source: None,
+    }
+}
+
+/// Explain a match against a file-type matcher, reporting the matched type's
+/// name (e.g. `rust`) in place of a glob, since a type can have many globs.
+fn explain_types(types: &Types, path: &str, is_dir: bool) -> Explanation {
+    match types.matched(Path::new(path), is_dir) {
+        ignore::Match::None => Explanation::none(),
+        ignore::Match::Ignore(glob) => Explanation {
+            result: MatchResult::Ignore,
+            glob: glob.file_type_def().map(|def| def.name().to_string()),
+            source: None,
+        },
+        ignore::Match::Whitelist(glob) => Explanation {
+            result: MatchResult::Whitelist,
+            glob: glob.file_type_def().map(|def| def.name().to_string()),
+            source: None,
+        },
+    }
+}
+
+/// Explain a match against any matcher kind stored behind a handle.
+fn explain_path_any(matcher: &Matcher, path: &str, is_dir: bool) -> Explanation {
+    match matcher {
+        Matcher::Single(gitignore) => explain_single(gitignore, path, is_dir),
+        Matcher::Layered(layers) => explain_layered(layers, path, is_dir),
+        Matcher::Overrides(overrides) => explain_overrides(overrides, path, is_dir),
+        Matcher::Composite(composite) => explain_composite(composite, path, is_dir),
+        Matcher::Types(types) => explain_types(types, path, is_dir),
+    }
+}
+
+/// Match a path against a file-type ("language") matcher.
+fn match_path_types(types: &Types, path: &str, is_dir: bool) -> MatchResult {
+    match types.matched(Path::new(path), is_dir) {
+        ignore::Match::None => MatchResult::None,
+        ignore::Match::Ignore(_) => MatchResult::Ignore,
+        ignore::Match::Whitelist(_) => MatchResult::Whitelist,
+    }
+}
+
+/// Match a path against an include/exclude override matcher. Unlike plain
+/// gitignore matching, an unmatched file path is `Ignore` rather than `None`
+/// when the set contains at least one whitelist glob; an unmatched directory
+/// is still `None` (see `Override::matched`).
+fn match_path_override(overrides: &Override, path: &str, is_dir: bool) -> MatchResult {
+    match overrides.matched(Path::new(path), is_dir) {
+        ignore::Match::None => MatchResult::None,
+        ignore::Match::Ignore(_) => MatchResult::Ignore,
+        ignore::Match::Whitelist(_) => MatchResult::Whitelist,
+    }
+}
+
+/// Match a path against any matcher kind stored behind a handle.
+fn match_path_any(matcher: &Matcher, path: &str, is_dir: bool) -> MatchResult {
+    match matcher {
+        Matcher::Single(gitignore) => match_path(gitignore, path, is_dir),
+        Matcher::Layered(layers) => match_path_layered(layers, path, is_dir),
+        Matcher::Overrides(overrides) => match_path_override(overrides, path, is_dir),
+        Matcher::Composite(composite) => match_path_composite(composite, path, is_dir),
+        Matcher::Types(types) => match_path_types(types, path, is_dir),
+    }
+}
+
+/// Build a `TypesBuilder` seeded with the built-in defaults plus any
+/// definitions registered via `register_custom_type`.
+fn types_builder_with_defaults() -> TypesBuilder {
+    let mut builder = TypesBuilder::new();
+    builder.add_defaults();
+    for (name, glob) in custom_types().iter() {
+        // register_custom_type already rejected anything that fails to
+        // compile, so this can't fail in practice; ignore the Result rather
+        // than unwrap so one future relaxation of that guarantee can't panic
+        // every build.
+        let _ = builder.add(name, glob);
+    }
+    builder
+}
+
+/// Parse a newline-separated list of names, skipping empty lines.
+/// Returns `None` if any line is not valid UTF-8.
+fn parse_names(bytes: &[u8]) -> Option<Vec<&str>> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    Some(text.split('\n').filter(|line| !line.is_empty()).collect())
+}
+
 /// Filter a newline-separated path list, returning only non-ignored entries.
 /// Paths ending in `/` are treated as directories; empty lines are skipped.
-fn filter_paths<'a>(gitignore: &Gitignore, paths: &'a str) -> Vec<&'a str> {
+fn filter_paths<'a>(matcher: &Matcher, paths: &'a str) -> Vec<&'a str> {
     let mut kept = Vec::new();
     for line in paths.split('\n') {
         if line.is_empty() {
@@ -67,7 +427,7 @@ fn filter_paths<'a>(gitignore: &Gitignore, paths: &'a str) -> Vec<&'a str> {
             (line, false)
         };
 
-        match match_path(gitignore, path_str, is_dir) {
+        match match_path_any(matcher, path_str, is_dir) {
             MatchResult::None | MatchResult::Whitelist => kept.push(line),
             MatchResult::Ignore => {}
         }
@@ -75,6 +435,24 @@ fn filter_paths<'a>(gitignore: &Gitignore, paths: &'a str) -> Vec<&'a str> {
     kept
 }
 
+/// Read a little-endian `i32` from `buf` at `*offset`, advancing it by 4.
+/// Returns `None` if the read would run past the end of the buffer.
+fn read_i32(buf: &[u8], offset: &mut usize) -> Option<i32> {
+    let end = offset.checked_add(4)?;
+    let bytes: [u8; 4] = buf.get(*offset..end)?.try_into().ok()?;
+    *offset = end;
+    Some(i32::from_le_bytes(bytes))
+}
+
+/// Read `len` bytes from `buf` at `*offset`, advancing it by `len`.
+/// Returns `None` if the read would run past the end of the buffer.
+fn read_bytes<'a>(buf: &'a [u8], offset: &mut usize, len: usize) -> Option<&'a [u8]> {
+    let end = offset.checked_add(len)?;
+    let slice = buf.get(*offset..end)?;
+    *offset = end;
+    Some(slice)
+}
+
 /// Allocate `size` bytes in WASM linear memory. Caller must call `dealloc`.
 #[no_mangle]
 pub extern "C" fn alloc(size: i32) -> i32 {
@@ -127,111 +505,285 @@ pub extern "C" fn create_matcher(patterns_ptr: i32, patterns_len: i32) -> i32 {
     };
 
     let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
-    matchers().insert(id, gitignore);
+    matchers().insert(id, Matcher::Single(gitignore));
     id as i32
 }
 
-/// Destroy a previously created matcher.
+/// Build an include/exclude override matcher from newline-separated globs,
+/// the way ripgrep's `--include`/`--exclude` overrides work. Non-UTF-8 lines
+/// are silently skipped.
+///
+/// A glob with no prefix is a whitelist (include-only) rule; a glob prefixed
+/// with `!` is an ignore rule. If the set contains at least one whitelist
+/// glob, a file path that matches none of the globs is treated as ignored
+/// (the "unmatched-ignore" case); with no whitelist globs, an unmatched file
+/// path returns `None` instead.
+///
+/// Returns a handle (> 0) on success, or:
+///  -1 = globs_len is negative
+///  -2 = globs_ptr is null when globs_len > 0
+///  -3 = builder.build() failed
 #[no_mangle]
-pub extern "C" fn destroy_matcher(handle: i32) {
-    if handle <= 0 {
-        return;
+pub extern "C" fn build_override_matcher(globs_ptr: i32, globs_len: i32) -> i32 {
+    if globs_len < 0 {
+        return -1;
     }
-    matchers().remove(&(handle as u32));
+
+    let bytes: &[u8] = if globs_len == 0 {
+        b""
+    } else {
+        if globs_ptr == 0 {
+            return -2;
+        }
+        unsafe { std::slice::from_raw_parts(globs_ptr as *const u8, globs_len as usize) }
+    };
+
+    let overrides = match build_overrides(bytes) {
+        Ok(ov) => ov,
+        Err(_) => return -3,
+    };
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    matchers().insert(id, Matcher::Overrides(overrides));
+    id as i32
 }
 
-/// Test whether a path matches the patterns in the given matcher.
-/// `is_dir`: 1 if the path is a directory, 0 otherwise.
+/// Create an empty composite matcher. Populate it with `add_source` before
+/// matching; an empty composite matches nothing.
+#[no_mangle]
+pub extern "C" fn create_composite_matcher() -> i32 {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    matchers().insert(id, Matcher::Composite(Composite::new()));
+    id as i32
+}
+
+/// Add a `.gitignore` source to a composite matcher, keyed by the directory
+/// it was read from. Sources rooted deeper than existing ones take
+/// precedence for paths under them, as in `match_path_composite`.
 ///
-/// Returns:
-///   0 = not matched,  1 = ignored,  2 = whitelisted (negation pattern)
-///  -1 = handle not positive,  -2 = null path_ptr or negative path_len
-///  -3 = path not valid UTF-8,  -4 = handle not found
+/// Returns 0 on success, or:
+///  -1 = handle not positive
+///  -2 = origin_len is negative or origin_ptr is null when origin_len > 0
+///  -3 = origin is not valid UTF-8
+///  -4 = patterns_len is negative or patterns_ptr is null when patterns_len > 0
+///  -5 = the patterns failed to compile
+///  -6 = handle not found or not a composite matcher
 #[no_mangle]
-pub extern "C" fn is_match(handle: i32, path_ptr: i32, path_len: i32, is_dir: i32) -> i32 {
+pub extern "C" fn add_source(
+    handle: i32,
+    origin_ptr: i32,
+    origin_len: i32,
+    patterns_ptr: i32,
+    patterns_len: i32,
+) -> i32 {
     if handle <= 0 {
         return -1;
     }
 
-    if path_len < 0 || (path_len > 0 && path_ptr == 0) {
+    if origin_len < 0 || (origin_len > 0 && origin_ptr == 0) {
         return -2;
     }
+    let origin_bytes: &[u8] = if origin_len == 0 {
+        b""
+    } else {
+        unsafe { std::slice::from_raw_parts(origin_ptr as *const u8, origin_len as usize) }
+    };
+    let origin_str = match std::str::from_utf8(origin_bytes) {
+        Ok(s) => s,
+        Err(_) => return -3,
+    };
 
-    let path_str = if path_len == 0 {
-        ""
+    if patterns_len < 0 || (patterns_len > 0 && patterns_ptr == 0) {
+        return -4;
+    }
+    let patterns_bytes: &[u8] = if patterns_len == 0 {
+        b""
     } else {
-        let bytes = unsafe { std::slice::from_raw_parts(path_ptr as *const u8, path_len as usize) };
-        match std::str::from_utf8(bytes) {
-            Ok(s) => s,
-            Err(_) => return -3,
-        }
+        unsafe { std::slice::from_raw_parts(patterns_ptr as *const u8, patterns_len as usize) }
     };
 
-    let gitignore = match matchers().get(&(handle as u32)) {
-        Some(gi) => gi,
-        None => return -4,
+    let gitignore = match build_matcher(patterns_bytes) {
+        Ok(gi) => gi,
+        Err(_) => return -5,
     };
 
-    match_path(gitignore, path_str, is_dir != 0) as i32
+    match matchers().get_mut(&(handle as u32)) {
+        Some(Matcher::Composite(composite)) => {
+            composite.add_source(PathBuf::from(origin_str), gitignore);
+            0
+        }
+        _ => -6,
+    }
 }
 
-/// Filter a newline-separated path list, keeping only non-ignored entries.
-/// `result_info_ptr` points to 8 WASM bytes where the result ptr+len are written;
-/// caller must `dealloc(result_ptr, result_len)` after reading (unless count==0).
+/// Build a file-type matcher selecting (and/or excluding) the named built-in
+/// or custom types, the way ripgrep's `--type`/`--type-not` flags do.
+/// `selected`/`negated` are newline-separated type names; empty lines are
+/// skipped. Pass `negated_len == 0` to select only.
 ///
-/// Returns count of kept paths (>= 0), or:
-///  -1 = handle not positive,  -2 = null result_info_ptr
-///  -3 = null paths_ptr or negative paths_len,  -4 = paths not valid UTF-8
-///  -5 = handle not found
+/// Returns a handle (> 0) on success, or:
+///  -1 = selected_len is negative or selected_ptr is null when selected_len > 0
+///  -2 = negated_len is negative or negated_ptr is null when negated_len > 0
+///  -3 = a type name is not valid UTF-8
+///  -4 = build failed (e.g. an unrecognized type name)
 #[no_mangle]
-pub extern "C" fn batch_filter(
-    handle: i32,
-    paths_ptr: i32,
-    paths_len: i32,
-    result_info_ptr: i32,
+pub extern "C" fn build_types_matcher(
+    selected_ptr: i32,
+    selected_len: i32,
+    negated_ptr: i32,
+    negated_len: i32,
 ) -> i32 {
-    if handle <= 0 {
+    if selected_len < 0 || (selected_len > 0 && selected_ptr == 0) {
         return -1;
     }
-
-    if result_info_ptr == 0 {
+    if negated_len < 0 || (negated_len > 0 && negated_ptr == 0) {
         return -2;
     }
 
-    if paths_len < 0 || (paths_len > 0 && paths_ptr == 0) {
-        return -3;
+    let selected_bytes: &[u8] = if selected_len == 0 {
+        b""
+    } else {
+        unsafe { std::slice::from_raw_parts(selected_ptr as *const u8, selected_len as usize) }
+    };
+    let negated_bytes: &[u8] = if negated_len == 0 {
+        b""
+    } else {
+        unsafe { std::slice::from_raw_parts(negated_ptr as *const u8, negated_len as usize) }
+    };
+
+    let selected = match parse_names(selected_bytes) {
+        Some(names) => names,
+        None => return -3,
+    };
+    let negated = match parse_names(negated_bytes) {
+        Some(names) => names,
+        None => return -3,
+    };
+
+    let mut builder = types_builder_with_defaults();
+    for name in &selected {
+        builder.select(name);
+    }
+    for name in &negated {
+        builder.negate(name);
     }
 
-    let text = if paths_len == 0 {
-        ""
+    let types = match builder.build() {
+        Ok(t) => t,
+        Err(_) => return -4,
+    };
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    matchers().insert(id, Matcher::Types(types));
+    id as i32
+}
+
+/// Register a custom file type definition (`name` + newline-separated
+/// globs) for use by subsequent `build_types_matcher` calls, alongside the
+/// built-in types. Registrations persist for the lifetime of the instance.
+///
+/// Each glob is compiled against a throwaway `TypesBuilder` before being
+/// stored, so a malformed entry is rejected here rather than silently
+/// dropped the next time `build_types_matcher` happens to run.
+///
+/// Returns 0 on success, or:
+///  -1 = name_len is negative or name_ptr is null when name_len > 0
+///  -2 = globs_len is negative or globs_ptr is null when globs_len > 0
+///  -3 = name or a glob is not valid UTF-8
+///  -4 = a glob failed to compile
+#[no_mangle]
+pub extern "C" fn register_custom_type(
+    name_ptr: i32,
+    name_len: i32,
+    globs_ptr: i32,
+    globs_len: i32,
+) -> i32 {
+    if name_len < 0 || (name_len > 0 && name_ptr == 0) {
+        return -1;
+    }
+    if globs_len < 0 || (globs_len > 0 && globs_ptr == 0) {
+        return -2;
+    }
+
+    let name_bytes: &[u8] = if name_len == 0 {
+        b""
     } else {
-        let bytes =
-            unsafe { std::slice::from_raw_parts(paths_ptr as *const u8, paths_len as usize) };
-        match std::str::from_utf8(bytes) {
-            Ok(s) => s,
-            Err(_) => return -4,
-        }
+        unsafe { std::slice::from_raw_parts(name_ptr as *const u8, name_len as usize) }
+    };
+    let globs_bytes: &[u8] = if globs_len == 0 {
+        b""
+    } else {
+        unsafe { std::slice::from_raw_parts(globs_ptr as *const u8, globs_len as usize) }
     };
 
-    let gitignore = match matchers().get(&(handle as u32)) {
-        Some(gi) => gi,
-        None => return -5,
+    let name = match std::str::from_utf8(name_bytes) {
+        Ok(s) => s,
+        Err(_) => return -3,
     };
+    let globs = match parse_names(globs_bytes) {
+        Some(g) => g,
+        None => return -3,
+    };
+
+    match register_custom_type_defs(name, &globs) {
+        Ok(()) => 0,
+        Err(code) => code,
+    }
+}
+
+/// Validate that every glob in `globs` compiles as a type definition named
+/// `name`, then store them in `custom_types()`. Split out so registration
+/// can be exercised directly in tests without reaching through a raw WASM
+/// pointer.
+///
+/// Returns `Err(-4)` if any glob fails to compile; nothing is stored in that
+/// case, matching `register_custom_type`'s all-or-nothing contract.
+fn register_custom_type_defs(name: &str, globs: &[&str]) -> Result<(), i32> {
+    for glob in globs {
+        let mut probe = TypesBuilder::new();
+        // `TypesBuilder::add` only validates `name`; the glob itself isn't
+        // compiled until `build`, so we have to build to catch a bad glob.
+        if probe.add(name, glob).is_err() || probe.select(name).build().is_err() {
+            return Err(-4);
+        }
+    }
+
+    for glob in globs {
+        custom_types().push((name.to_string(), glob.to_string()));
+    }
+    Ok(())
+}
+
+/// Enumerate the names of the built-in file types (e.g. `rust`, `js`), plus
+/// any registered via `register_custom_type`, as a newline-separated list.
+/// `result_info_ptr` points to 8 WASM bytes where the result ptr+len are
+/// written; caller must `dealloc(result_ptr, result_len)` after reading
+/// (unless count==0).
+///
+/// Returns the count of type names (>= 0), or -1 if result_info_ptr is null.
+#[no_mangle]
+pub extern "C" fn list_type_names(result_info_ptr: i32) -> i32 {
+    if result_info_ptr == 0 {
+        return -1;
+    }
 
-    let kept = filter_paths(gitignore, text);
+    let names: Vec<String> = types_builder_with_defaults()
+        .definitions()
+        .iter()
+        .map(|def| def.name().to_string())
+        .collect();
 
     let result_info = unsafe { std::slice::from_raw_parts_mut(result_info_ptr as *mut u8, 8) };
 
-    let count = kept.len() as i32;
+    let count = names.len() as i32;
 
-    if kept.is_empty() {
+    if names.is_empty() {
         result_info[0..4].copy_from_slice(&0i32.to_le_bytes());
         result_info[4..8].copy_from_slice(&0i32.to_le_bytes());
         return 0;
     }
 
-    let result_str = kept.join("\n");
-    let result_bytes = result_str.into_bytes();
+    let result_bytes = names.join("\n").into_bytes();
     let result_len = result_bytes.len();
 
     // Leak the buffer; caller must dealloc via Vec::from_raw_parts.
@@ -245,592 +797,1872 @@ pub extern "C" fn batch_filter(
     count
 }
 
-// Tests exercise core logic directly (no FFI/pointer concerns) and run on any host.
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn matcher(patterns: &[&str]) -> Gitignore {
-        build_matcher(patterns.join("\n").as_bytes()).expect("patterns should compile")
-    }
-
-    fn matches_file(gi: &Gitignore, path: &str) -> MatchResult {
-        match_path(gi, path, false)
+/// Build a composite matcher from several pre-discovered `(origin, patterns)`
+/// sources in one call, instead of `create_composite_matcher` followed by
+/// `count` separate `add_source` round trips.
+///
+/// This is for hosts that locate and read the `.gitignore`/`.ignore` files
+/// themselves: the discovered source list is handed off here in a single
+/// round trip rather than one `add_source` call per file. No filesystem
+/// access happens in this call. `build_matcher_from_path` instead does the
+/// walk up to the VCS root inside this module, through the host imports.
+///
+/// `sources_ptr`/`sources_len` use the same buffer layout as
+/// `create_layered_matcher`: `count` records of
+///   `i32 origin_len | origin_len bytes (UTF-8 origin) | i32 patterns_len | patterns_len bytes`
+///
+/// Returns a handle (> 0) on success, or:
+///  -1 = count is negative
+///  -2 = sources_len is negative
+///  -3 = sources_ptr is null when sources_len > 0
+///  -4 = buffer is truncated or a length is negative
+///  -5 = an origin path is not valid UTF-8
+///  -6 = a source's patterns failed to compile
+#[no_mangle]
+pub extern "C" fn build_matcher_from_sources(
+    sources_ptr: i32,
+    sources_len: i32,
+    count: i32,
+) -> i32 {
+    if count < 0 {
+        return -1;
     }
-
-    fn matches_dir(gi: &Gitignore, path: &str) -> MatchResult {
-        match_path(gi, path, true)
+    if sources_len < 0 {
+        return -2;
     }
-
-    fn batch(gi: &Gitignore, paths: &[&str]) -> Vec<String> {
-        let input = paths.join("\n");
-        filter_paths(gi, &input)
-            .into_iter()
-            .map(String::from)
-            .collect()
+    if sources_len > 0 && sources_ptr == 0 {
+        return -3;
+    }
+
+    let buf: &[u8] = if sources_len == 0 {
+        b""
+    } else {
+        unsafe { std::slice::from_raw_parts(sources_ptr as *const u8, sources_len as usize) }
+    };
+
+    let composite = match composite_from_buffer(buf, count) {
+        Ok(c) => c,
+        Err(code) => return code,
+    };
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    matchers().insert(id, Matcher::Composite(composite));
+    id as i32
+}
+
+/// Parse `count` `(origin, patterns)` records out of `buf` (see
+/// `build_matcher_from_sources` for the layout) and fold them into a
+/// `Composite`. Split out so the parsing logic can be exercised directly in
+/// tests without reaching through a raw WASM pointer.
+fn composite_from_buffer(buf: &[u8], count: i32) -> Result<Composite, i32> {
+    let mut composite = Composite::new();
+    parse_tagged_records(buf, count, |origin, gitignore| {
+        composite.add_source(PathBuf::from(origin), gitignore);
+    })?;
+    Ok(composite)
+}
+
+/// Parse `count` back-to-back `(tag, patterns)` records out of `buf`, shared
+/// by every constructor that takes a buffer of this shape
+/// (`create_layered_matcher`, `composite_from_buffer`): each record is
+///   `i32 tag_len | tag_len bytes (UTF-8 tag) | i32 patterns_len | patterns_len bytes`
+/// `tag` is a base/origin path and `patterns` compiles via `build_matcher`;
+/// `on_record` is called once per parsed pair, in buffer order, so callers
+/// can fold records into whatever shape they need (a sorted `Vec`, a
+/// `Composite` trie, ...) without duplicating the parsing loop.
+///
+/// Returns `Err` with the same codes documented on those callers: `-4` for a
+/// truncated buffer or negative length, `-5` for a non-UTF-8 tag, `-6` for
+/// patterns that failed to compile.
+fn parse_tagged_records(
+    buf: &[u8],
+    count: i32,
+    mut on_record: impl FnMut(&str, Gitignore),
+) -> Result<(), i32> {
+    let mut offset = 0usize;
+
+    for _ in 0..count {
+        let tag_len = match read_i32(buf, &mut offset) {
+            Some(v) if v >= 0 => v as usize,
+            _ => return Err(-4),
+        };
+        let tag_bytes = match read_bytes(buf, &mut offset, tag_len) {
+            Some(b) => b,
+            None => return Err(-4),
+        };
+        let tag = match std::str::from_utf8(tag_bytes) {
+            Ok(s) => s,
+            Err(_) => return Err(-5),
+        };
+
+        let patterns_len = match read_i32(buf, &mut offset) {
+            Some(v) if v >= 0 => v as usize,
+            _ => return Err(-4),
+        };
+        let patterns_bytes = match read_bytes(buf, &mut offset, patterns_len) {
+            Some(b) => b,
+            None => return Err(-4),
+        };
+
+        let gitignore = match build_matcher(patterns_bytes) {
+            Ok(gi) => gi,
+            Err(_) => return Err(-6),
+        };
+
+        on_record(tag, gitignore);
+    }
+
+    Ok(())
+}
+
+/// Auto-discover ignore files by walking from `start_dir` upward to the
+/// repository root, as watchexec does, and build a single composite handle
+/// from what's found along the way — so Go callers don't have to locate,
+/// read, or order the ignore files themselves.
+///
+/// At every directory visited, a `.ignore` file (the ripgrep/fd/watchexec
+/// convention for VCS-agnostic ignore rules) is always picked up; a
+/// `.gitignore` is additionally picked up when `use_gitignore` is non-zero.
+/// The walk stops as soon as a directory containing `.git` is found (that
+/// directory's own ignore files are still included before stopping), or at
+/// the filesystem root if `.git` is never found.
+///
+/// Locating and reading these files needs real directory/file access, which
+/// `wasm32-unknown-unknown` doesn't have on its own — see `host_path_exists`
+/// and `host_read_file` for the host-import contract this relies on there.
+///
+/// Returns a handle (> 0) on success, or:
+///  -1 = start_dir_len is negative
+///  -2 = start_dir_ptr is null when start_dir_len > 0
+///  -3 = start_dir is not valid UTF-8
+///  -6 = an ignore file's contents failed to compile
+#[no_mangle]
+pub extern "C" fn build_matcher_from_path(
+    start_dir_ptr: i32,
+    start_dir_len: i32,
+    use_gitignore: i32,
+) -> i32 {
+    if start_dir_len < 0 {
+        return -1;
+    }
+    if start_dir_len > 0 && start_dir_ptr == 0 {
+        return -2;
+    }
+
+    let start_bytes: &[u8] = if start_dir_len == 0 {
+        b""
+    } else {
+        unsafe { std::slice::from_raw_parts(start_dir_ptr as *const u8, start_dir_len as usize) }
+    };
+    let start_str = match std::str::from_utf8(start_bytes) {
+        Ok(s) => s,
+        Err(_) => return -3,
+    };
+
+    let composite = match discover_composite(Path::new(start_str), use_gitignore != 0) {
+        Ok(c) => c,
+        Err(code) => return code,
+    };
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    matchers().insert(id, Matcher::Composite(composite));
+    id as i32
+}
+
+/// Walk logic behind `build_matcher_from_path`, split out so it can be
+/// exercised directly in tests against a real temp directory without
+/// reaching through a raw WASM pointer.
+fn discover_composite(start_dir: &Path, use_gitignore: bool) -> Result<Composite, i32> {
+    let mut composite = Composite::new();
+    let mut dir = start_dir.to_path_buf();
+
+    loop {
+        let mut patterns = Vec::new();
+        if let Some(bytes) = read_host_file(&dir.join(".ignore")) {
+            patterns.extend_from_slice(&bytes);
+            patterns.push(b'\n');
+        }
+        if use_gitignore {
+            if let Some(bytes) = read_host_file(&dir.join(".gitignore")) {
+                patterns.extend_from_slice(&bytes);
+                patterns.push(b'\n');
+            }
+        }
+        if !patterns.is_empty() {
+            match build_matcher(&patterns) {
+                Ok(gitignore) => composite.add_source(dir.clone(), gitignore),
+                Err(_) => return Err(-6),
+            }
+        }
+
+        if host_exists(&dir.join(".git")) {
+            break;
+        }
+        match dir.parent() {
+            Some(parent) => dir = parent.to_path_buf(),
+            None => break,
+        }
+    }
+
+    Ok(composite)
+}
+
+/// `host_path_exists` wrapper used by `build_matcher_from_path` to recognize
+/// a VCS root. On `wasm32-unknown-unknown` this marshals `path` through the
+/// host import; everywhere else (this crate's own test suite) there's a real
+/// filesystem right here, so it asks that directly instead of pretending to
+/// cross a host boundary that doesn't exist on this target.
+#[cfg(target_arch = "wasm32")]
+fn host_exists(path: &Path) -> bool {
+    let path_str = path.to_string_lossy();
+    let path_bytes = path_str.as_bytes();
+    unsafe { host_path_exists(path_bytes.as_ptr() as i32, path_bytes.len() as i32) != 0 }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn host_exists(path: &Path) -> bool {
+    path.exists()
+}
+
+/// `host_read_file` wrapper used by `build_matcher_from_path`: returns the
+/// file's bytes, or `None` if it doesn't exist. On `wasm32-unknown-unknown`
+/// this marshals `path` through the host import and frees the host-allocated
+/// buffer after copying it; everywhere else it reads straight off disk.
+#[cfg(target_arch = "wasm32")]
+fn read_host_file(path: &Path) -> Option<Vec<u8>> {
+    let path_str = path.to_string_lossy();
+    let path_bytes = path_str.as_bytes();
+    let mut info = [0u8; 8];
+    let found = unsafe {
+        host_read_file(path_bytes.as_ptr() as i32, path_bytes.len() as i32, info.as_mut_ptr() as i32)
+    };
+    if found == 0 {
+        return None;
+    }
+
+    let ptr = i32::from_le_bytes(info[0..4].try_into().unwrap());
+    let len = i32::from_le_bytes(info[4..8].try_into().unwrap());
+    let content = if len == 0 {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(ptr as *const u8, len as usize).to_vec() }
+    };
+    dealloc(ptr, len);
+    Some(content)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_host_file(path: &Path) -> Option<Vec<u8>> {
+    std::fs::read(path).ok()
+}
+
+
+
+/// Create a layered matcher from several `(base_path, patterns)` pairs, for
+/// repositories with `.gitignore` files at multiple directory depths.
+///
+/// `layers_ptr`/`layers_len` describe a WASM-memory buffer encoding `count`
+/// records back to back, each record laid out as:
+///   `i32 base_len | base_len bytes (UTF-8 base path) | i32 patterns_len | patterns_len bytes`
+///
+/// Layers are matched deepest-base-first: the first layer whose base is an
+/// ancestor of the queried path and that returns a decisive `Ignore`/`Whitelist`
+/// wins, mirroring git's closest-`.gitignore`-wins precedence.
+///
+/// Returns a handle (> 0) on success, or:
+///  -1 = count is negative
+///  -2 = layers_len is negative
+///  -3 = layers_ptr is null when layers_len > 0
+///  -4 = buffer is truncated or a length is negative
+///  -5 = a base path is not valid UTF-8
+///  -6 = a layer's patterns failed to compile
+#[no_mangle]
+pub extern "C" fn create_layered_matcher(layers_ptr: i32, layers_len: i32, count: i32) -> i32 {
+    if count < 0 {
+        return -1;
+    }
+    if layers_len < 0 {
+        return -2;
+    }
+    if layers_len > 0 && layers_ptr == 0 {
+        return -3;
+    }
+
+    let buf: &[u8] = if layers_len == 0 {
+        b""
+    } else {
+        unsafe { std::slice::from_raw_parts(layers_ptr as *const u8, layers_len as usize) }
+    };
+
+    let mut layers: Vec<(PathBuf, Gitignore)> = Vec::with_capacity(count.max(0) as usize);
+    if let Err(code) = parse_tagged_records(buf, count, |base, gitignore| {
+        layers.push((PathBuf::from(base), gitignore));
+    }) {
+        return code;
+    }
+
+    layers.sort_by_key(|(base, _)| std::cmp::Reverse(base.components().count()));
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    matchers().insert(id, Matcher::Layered(layers));
+    id as i32
+}
+
+/// Destroy a previously created matcher.
+#[no_mangle]
+pub extern "C" fn destroy_matcher(handle: i32) {
+    if handle <= 0 {
+        return;
+    }
+    matchers().remove(&(handle as u32));
+}
+
+/// Returns whether a directory walker should descend into `path`, i.e.
+/// whether it is *not* decisively ignored by the matcher. This is the same
+/// prune-at-the-boundary check `walk_and_filter` applies automatically at
+/// every directory; use this one directly when the host wants to drive its
+/// own traversal instead (e.g. to interleave it with other per-directory
+/// work), without descending into or even `stat`-ing a pruned subtree's
+/// contents — the main performance win over repeatedly calling
+/// `batch_filter` on every nested path.
+///
+/// Returns:
+///   0 = ignored; caller should prune (skip this directory and its contents)
+///   1 = not ignored; caller should descend
+///  -1 = handle not positive,  -2 = null path_ptr or negative path_len
+///  -3 = path not valid UTF-8,  -4 = handle not found
+#[no_mangle]
+pub extern "C" fn should_descend(handle: i32, path_ptr: i32, path_len: i32) -> i32 {
+    if handle <= 0 {
+        return -1;
+    }
+
+    if path_len < 0 || (path_len > 0 && path_ptr == 0) {
+        return -2;
+    }
+
+    let path_str = if path_len == 0 {
+        ""
+    } else {
+        let bytes = unsafe { std::slice::from_raw_parts(path_ptr as *const u8, path_len as usize) };
+        match std::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(_) => return -3,
+        }
+    };
+
+    let matcher = match matchers().get(&(handle as u32)) {
+        Some(m) => m,
+        None => return -4,
+    };
+
+    match match_path_any(matcher, path_str, true) {
+        MatchResult::Ignore => 0,
+        MatchResult::None | MatchResult::Whitelist => 1,
+    }
+}
+
+/// Test whether a path matches the patterns in the given matcher.
+/// `is_dir`: 1 if the path is a directory, 0 otherwise.
+///
+/// Returns:
+///   0 = not matched,  1 = ignored,  2 = whitelisted (negation pattern)
+///  -1 = handle not positive,  -2 = null path_ptr or negative path_len
+///  -3 = path not valid UTF-8,  -4 = handle not found
+#[no_mangle]
+pub extern "C" fn is_match(handle: i32, path_ptr: i32, path_len: i32, is_dir: i32) -> i32 {
+    if handle <= 0 {
+        return -1;
+    }
+
+    if path_len < 0 || (path_len > 0 && path_ptr == 0) {
+        return -2;
+    }
+
+    let path_str = if path_len == 0 {
+        ""
+    } else {
+        let bytes = unsafe { std::slice::from_raw_parts(path_ptr as *const u8, path_len as usize) };
+        match std::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(_) => return -3,
+        }
+    };
+
+    let matcher = match matchers().get(&(handle as u32)) {
+        Some(m) => m,
+        None => return -4,
+    };
+
+    match_path_any(matcher, path_str, is_dir != 0) as i32
+}
+
+/// Write an optional string's ptr/len into an 8-byte slot, leaking the
+/// allocation for the caller to `dealloc` (0/0 when `value` is `None`).
+fn write_optional_string(slot: &mut [u8], value: Option<String>) {
+    let (ptr, len) = match value {
+        Some(s) => {
+            let bytes = s.into_bytes();
+            let len = bytes.len();
+            let mut buf = bytes.into_boxed_slice();
+            let ptr = buf.as_mut_ptr();
+            std::mem::forget(buf);
+            (ptr as i32, len as i32)
+        }
+        None => (0, 0),
+    };
+    slot[0..4].copy_from_slice(&ptr.to_le_bytes());
+    slot[4..8].copy_from_slice(&len.to_le_bytes());
+}
+
+/// Explain why a path matched (or didn't), reporting the glob that decided
+/// it and, for multi-source matchers, the origin it came from — a
+/// `git check-ignore -v`-style diagnostic, modeled on the `Glob` reporting
+/// types in ripgrep's `ignore` crate (`gitignore::Glob`, `overrides::Glob`).
+///
+/// Writes 24 bytes to `info_ptr`:
+///   `i32 result | i32 line_number | i32 glob_ptr | i32 glob_len | i32 source_ptr | i32 source_len`
+///
+/// `result` is a `MatchResult` code. `line_number` is always -1: the
+/// `ignore` crate doesn't expose per-glob line numbers through its public
+/// API. `glob_ptr`/`glob_len` and `source_ptr`/`source_len` are 0 when no
+/// glob or source origin is available — including the override
+/// "unmatched but still ignored" case, and matcher kinds (`Overrides`)
+/// whose crate API doesn't expose matched glob text at all. Caller must
+/// `free_match_info` the allocated strings.
+///
+/// Returns 0 on success, or:
+///  -1 = handle not positive,  -2 = null path_ptr or negative path_len
+///  -3 = path not valid UTF-8,  -4 = handle not found,  -5 = info_ptr is null
+#[no_mangle]
+pub extern "C" fn explain_match(
+    handle: i32,
+    path_ptr: i32,
+    path_len: i32,
+    is_dir: i32,
+    info_ptr: i32,
+) -> i32 {
+    if handle <= 0 {
+        return -1;
+    }
+
+    if path_len < 0 || (path_len > 0 && path_ptr == 0) {
+        return -2;
+    }
+
+    if info_ptr == 0 {
+        return -5;
+    }
+
+    let path_str = if path_len == 0 {
+        ""
+    } else {
+        let bytes = unsafe { std::slice::from_raw_parts(path_ptr as *const u8, path_len as usize) };
+        match std::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(_) => return -3,
+        }
+    };
+
+    let matcher = match matchers().get(&(handle as u32)) {
+        Some(m) => m,
+        None => return -4,
+    };
+
+    let explanation = explain_path_any(matcher, path_str, is_dir != 0);
+
+    let info = unsafe { std::slice::from_raw_parts_mut(info_ptr as *mut u8, 24) };
+    info[0..4].copy_from_slice(&(explanation.result as i32).to_le_bytes());
+    info[4..8].copy_from_slice(&(-1i32).to_le_bytes());
+    write_optional_string(&mut info[8..16], explanation.glob);
+    write_optional_string(&mut info[16..24], explanation.source);
+
+    0
+}
+
+/// Free the heap-allocated glob/source strings written by `explain_match`.
+/// A no-op for any pair whose ptr/len is 0 (matches `dealloc`'s behavior).
+#[no_mangle]
+pub extern "C" fn free_match_info(glob_ptr: i32, glob_len: i32, source_ptr: i32, source_len: i32) {
+    dealloc(glob_ptr, glob_len);
+    dealloc(source_ptr, source_len);
+}
+
+/// Filter a newline-separated path list, keeping only non-ignored entries.
+/// `result_info_ptr` points to 8 WASM bytes where the result ptr+len are written;
+/// caller must `dealloc(result_ptr, result_len)` after reading (unless count==0).
+///
+/// Returns count of kept paths (>= 0), or:
+///  -1 = handle not positive,  -2 = null result_info_ptr
+///  -3 = null paths_ptr or negative paths_len,  -4 = paths not valid UTF-8
+///  -5 = handle not found
+#[no_mangle]
+pub extern "C" fn batch_filter(
+    handle: i32,
+    paths_ptr: i32,
+    paths_len: i32,
+    result_info_ptr: i32,
+) -> i32 {
+    if handle <= 0 {
+        return -1;
+    }
+
+    if result_info_ptr == 0 {
+        return -2;
+    }
+
+    if paths_len < 0 || (paths_len > 0 && paths_ptr == 0) {
+        return -3;
+    }
+
+    let text = if paths_len == 0 {
+        ""
+    } else {
+        let bytes =
+            unsafe { std::slice::from_raw_parts(paths_ptr as *const u8, paths_len as usize) };
+        match std::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(_) => return -4,
+        }
+    };
+
+    let matcher = match matchers().get(&(handle as u32)) {
+        Some(m) => m,
+        None => return -5,
+    };
+
+    let kept = filter_paths(matcher, text);
+
+    let result_info = unsafe { std::slice::from_raw_parts_mut(result_info_ptr as *mut u8, 8) };
+
+    let count = kept.len() as i32;
+
+    if kept.is_empty() {
+        result_info[0..4].copy_from_slice(&0i32.to_le_bytes());
+        result_info[4..8].copy_from_slice(&0i32.to_le_bytes());
+        return 0;
+    }
+
+    let result_str = kept.join("\n");
+    let result_bytes = result_str.into_bytes();
+    let result_len = result_bytes.len();
+
+    // Leak the buffer; caller must dealloc via Vec::from_raw_parts.
+    let mut result_buf = result_bytes.into_boxed_slice();
+    let result_ptr = result_buf.as_mut_ptr();
+    std::mem::forget(result_buf);
+
+    result_info[0..4].copy_from_slice(&(result_ptr as i32).to_le_bytes());
+    result_info[4..8].copy_from_slice(&(result_len as i32).to_le_bytes());
+
+    count
+}
+
+/// Recursively walk `root` under the matcher behind `handle`, pruning whole
+/// ignored subtrees at the boundary (the same decision `should_descend`
+/// exposes standalone) instead of stat-ing and testing every nested path,
+/// and collecting the surviving file paths.
+///
+/// The recursion itself needs to read directory entries, which
+/// `wasm32-unknown-unknown` can't do on its own — see `host_list_dir` for
+/// the host-import contract this relies on there. That target also has no
+/// thread-spawning API, so a single call here walks single-threaded; true
+/// parallelism is still available to the host, since a call only reads the
+/// matcher behind `handle` and touches no other shared state — a Go caller
+/// can run several of these concurrently over disjoint subtrees (each in
+/// its own goroutine driving its own wasm instance) and merge the results.
+///
+/// `result_info_ptr` points to 8 WASM bytes where the newline-joined result
+/// ptr+len are written; caller must `dealloc(result_ptr, result_len)` after
+/// reading (unless count == 0).
+///
+/// Returns count of kept file paths (>= 0), or:
+///  -1 = handle not positive,  -2 = null result_info_ptr
+///  -3 = null root_ptr or negative root_len,  -4 = root not valid UTF-8
+///  -5 = handle not found
+#[no_mangle]
+pub extern "C" fn walk_and_filter(
+    handle: i32,
+    root_ptr: i32,
+    root_len: i32,
+    result_info_ptr: i32,
+) -> i32 {
+    if handle <= 0 {
+        return -1;
+    }
+
+    if result_info_ptr == 0 {
+        return -2;
+    }
+
+    if root_len < 0 || (root_len > 0 && root_ptr == 0) {
+        return -3;
+    }
+
+    let root_bytes: &[u8] = if root_len == 0 {
+        b""
+    } else {
+        unsafe { std::slice::from_raw_parts(root_ptr as *const u8, root_len as usize) }
+    };
+    let root_str = match std::str::from_utf8(root_bytes) {
+        Ok(s) => s,
+        Err(_) => return -4,
+    };
+
+    let matcher = match matchers().get(&(handle as u32)) {
+        Some(m) => m,
+        None => return -5,
+    };
+
+    let mut kept = Vec::new();
+    walk_dir(matcher, Path::new(root_str), &mut kept);
+
+    let result_info = unsafe { std::slice::from_raw_parts_mut(result_info_ptr as *mut u8, 8) };
+    let count = kept.len() as i32;
+
+    if kept.is_empty() {
+        result_info[0..4].copy_from_slice(&0i32.to_le_bytes());
+        result_info[4..8].copy_from_slice(&0i32.to_le_bytes());
+        return 0;
+    }
+
+    let result_str = kept.join("\n");
+    let result_bytes = result_str.into_bytes();
+    let result_len = result_bytes.len();
+
+    let mut result_buf = result_bytes.into_boxed_slice();
+    let result_ptr = result_buf.as_mut_ptr();
+    std::mem::forget(result_buf);
+
+    result_info[0..4].copy_from_slice(&(result_ptr as i32).to_le_bytes());
+    result_info[4..8].copy_from_slice(&(result_len as i32).to_le_bytes());
+
+    count
+}
+
+/// Recursion behind `walk_and_filter`: lists `dir` via `list_host_dir`,
+/// prunes subdirectories decisively ignored by `matcher`, and recurses into
+/// the rest, pushing every surviving file's full path onto `kept`.
+fn walk_dir(matcher: &Matcher, dir: &Path, kept: &mut Vec<String>) {
+    let entries = match list_host_dir(dir) {
+        Some(e) => e,
+        None => return,
+    };
+
+    for (name, is_dir) in entries {
+        let path = dir.join(&name);
+        let path_str = path.to_string_lossy();
+
+        if is_dir {
+            if !matches!(match_path_any(matcher, &path_str, true), MatchResult::Ignore) {
+                walk_dir(matcher, &path, kept);
+            }
+        } else if !matches!(match_path_any(matcher, &path_str, false), MatchResult::Ignore) {
+            kept.push(path_str.into_owned());
+        }
+    }
+}
+
+/// `host_list_dir` wrapper used by `walk_dir`: returns `(name, is_dir)` for
+/// each entry, or `None` if `dir` isn't a readable directory. On
+/// `wasm32-unknown-unknown` this marshals `dir` through the host import and
+/// decodes its record buffer, freeing the host-allocated copy afterward;
+/// everywhere else it reads the directory straight off disk.
+#[cfg(target_arch = "wasm32")]
+fn list_host_dir(dir: &Path) -> Option<Vec<(String, bool)>> {
+    let dir_str = dir.to_string_lossy();
+    let dir_bytes = dir_str.as_bytes();
+    let mut info = [0u8; 8];
+    let count = unsafe {
+        host_list_dir(dir_bytes.as_ptr() as i32, dir_bytes.len() as i32, info.as_mut_ptr() as i32)
+    };
+    if count < 0 {
+        return None;
+    }
+
+    let ptr = i32::from_le_bytes(info[0..4].try_into().unwrap());
+    let len = i32::from_le_bytes(info[4..8].try_into().unwrap());
+    let buf: &[u8] = if len == 0 {
+        b""
+    } else {
+        unsafe { std::slice::from_raw_parts(ptr as *const u8, len as usize) }
+    };
+
+    let mut offset = 0usize;
+    let mut entries = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let name_len = read_i32(buf, &mut offset).unwrap_or(0).max(0) as usize;
+        let name_bytes = read_bytes(buf, &mut offset, name_len).unwrap_or(b"");
+        let name = std::str::from_utf8(name_bytes).unwrap_or("").to_string();
+        let is_dir = read_i32(buf, &mut offset).unwrap_or(0) != 0;
+        entries.push((name, is_dir));
+    }
+
+    dealloc(ptr, len);
+    Some(entries)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn list_host_dir(dir: &Path) -> Option<Vec<(String, bool)>> {
+    let entries = std::fs::read_dir(dir).ok()?;
+    Some(
+        entries
+            .flatten()
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                (name, is_dir)
+            })
+            .collect(),
+    )
+}
+
+// Tests exercise core logic directly (no FFI/pointer concerns) and run on any host.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(patterns: &[&str]) -> Gitignore {
+        build_matcher(patterns.join("\n").as_bytes()).expect("patterns should compile")
+    }
+
+    fn matches_file(gi: &Gitignore, path: &str) -> MatchResult {
+        match_path(gi, path, false)
+    }
+
+    fn matches_dir(gi: &Gitignore, path: &str) -> MatchResult {
+        match_path(gi, path, true)
+    }
+
+    fn batch(gi: &Gitignore, paths: &[&str]) -> Vec<String> {
+        let input = paths.join("\n");
+        filter_paths(&Matcher::Single(gi.clone()), &input)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    // -----------------------------------------------------------------------
+    // build_matcher
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn build_empty_patterns() {
+        let gi = build_matcher(b"").expect("empty patterns should compile");
+        assert!(gi.is_empty());
+    }
+
+    #[test]
+    fn build_single_pattern() {
+        let gi = build_matcher(b"*.log").expect("should compile");
+        assert_eq!(gi.num_ignores(), 1);
+    }
+
+    #[test]
+    fn build_multiple_patterns() {
+        let gi = build_matcher(b"*.log\nbuild/\ntemp*").expect("should compile");
+        assert_eq!(gi.num_ignores(), 3);
+    }
+
+    #[test]
+    fn build_with_comments_and_blanks() {
+        let gi = build_matcher(b"# this is a comment\n\n*.log\n\n# another comment\nbuild/")
+            .expect("should compile");
+        assert_eq!(gi.num_ignores(), 2);
+    }
+
+    #[test]
+    fn build_with_negation() {
+        let gi = build_matcher(b"*.log\n!important.log").expect("should compile");
+        assert_eq!(gi.num_ignores(), 1);
+        assert_eq!(gi.num_whitelists(), 1);
+    }
+
+    // -----------------------------------------------------------------------
+    // match_path — basic globs
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn match_star_extension() {
+        let gi = matcher(&["*.log"]);
+        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "error.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "app.txt"), MatchResult::None);
+        assert_eq!(matches_file(&gi, "src/debug.log"), MatchResult::Ignore);
+    }
+
+    #[test]
+    fn match_exact_filename() {
+        let gi = matcher(&["Thumbs.db"]);
+        assert_eq!(matches_file(&gi, "Thumbs.db"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "src/Thumbs.db"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "thumbs.db"), MatchResult::None);
+    }
+
+    #[test]
+    fn match_prefix_star() {
+        let gi = matcher(&["temp*"]);
+        assert_eq!(matches_file(&gi, "tempfile"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "temporary.txt"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "atemp"), MatchResult::None);
+    }
+
+    #[test]
+    fn match_double_star() {
+        let gi = matcher(&["**/logs"]);
+        assert_eq!(matches_file(&gi, "logs"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "a/logs"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "a/b/logs"), MatchResult::Ignore);
+    }
+
+    #[test]
+    fn match_double_star_with_extension() {
+        let gi = matcher(&["**/*.log"]);
+        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "a/debug.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "a/b/c/debug.log"), MatchResult::Ignore);
+    }
+
+    #[test]
+    fn match_question_mark() {
+        let gi = matcher(&["debug?.log"]);
+        assert_eq!(matches_file(&gi, "debug0.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "debugA.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::None);
+        assert_eq!(matches_file(&gi, "debug10.log"), MatchResult::None);
+    }
+
+    #[test]
+    fn match_character_class() {
+        let gi = matcher(&["debug[0-9].log"]);
+        assert_eq!(matches_file(&gi, "debug0.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "debug9.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "debugA.log"), MatchResult::None);
+    }
+
+    // -----------------------------------------------------------------------
+    // match_path — directory patterns
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn match_directory_trailing_slash_pattern() {
+        // Pattern "build/" should only match directories, not files named "build"
+        let gi = matcher(&["build/"]);
+        assert_eq!(matches_dir(&gi, "build"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "build"), MatchResult::None);
+        assert_eq!(matches_dir(&gi, "src/build"), MatchResult::Ignore);
+    }
+
+    #[test]
+    fn match_directory_without_trailing_slash_pattern() {
+        // Pattern "build" without trailing slash matches both files and dirs
+        let gi = matcher(&["build"]);
+        assert_eq!(matches_file(&gi, "build"), MatchResult::Ignore);
+        assert_eq!(matches_dir(&gi, "build"), MatchResult::Ignore);
+    }
+
+    #[test]
+    fn match_nested_directory_pattern() {
+        let gi = matcher(&["logs/**/debug.log"]);
+        assert_eq!(matches_file(&gi, "logs/debug.log"), MatchResult::Ignore);
+        assert_eq!(
+            matches_file(&gi, "logs/monday/debug.log"),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            matches_file(&gi, "logs/monday/pm/debug.log"),
+            MatchResult::Ignore
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // match_path — negation patterns
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn negation_basic() {
+        let gi = matcher(&["*.log", "!important.log"]);
+        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "error.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "important.log"), MatchResult::Whitelist);
+    }
+
+    #[test]
+    fn negation_order_matters() {
+        // In gitignore, later patterns override earlier ones
+        let gi = matcher(&["*.log", "!important.log", "important.log"]);
+        // The last pattern re-ignores important.log
+        assert_eq!(matches_file(&gi, "important.log"), MatchResult::Ignore);
+    }
+
+    #[test]
+    fn negation_of_directory() {
+        let gi = matcher(&["build/", "!build/"]);
+        assert_eq!(matches_dir(&gi, "build"), MatchResult::Whitelist);
+    }
+
+    // -----------------------------------------------------------------------
+    // match_path — rooted / anchored patterns
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn rooted_pattern_with_leading_slash() {
+        // A leading slash anchors the pattern to the root
+        let gi = matcher(&["/build"]);
+        assert_eq!(matches_file(&gi, "build"), MatchResult::Ignore);
+        // Should NOT match in subdirectories
+        assert_eq!(matches_file(&gi, "src/build"), MatchResult::None);
+    }
+
+    #[test]
+    fn pattern_with_middle_slash_is_anchored() {
+        // A pattern containing a slash (other than trailing) is anchored
+        let gi = matcher(&["doc/frotz"]);
+        assert_eq!(matches_file(&gi, "doc/frotz"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "a/doc/frotz"), MatchResult::None);
+    }
+
+    // -----------------------------------------------------------------------
+    // match_path — edge cases
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn empty_matcher_matches_nothing() {
+        let gi = matcher(&[]);
+        assert_eq!(matches_file(&gi, "anything.txt"), MatchResult::None);
+        assert_eq!(matches_dir(&gi, "anydir"), MatchResult::None);
+    }
+
+    #[test]
+    fn comments_only_matcher_matches_nothing() {
+        let gi = matcher(&["# just a comment", "# another comment"]);
+        assert_eq!(matches_file(&gi, "anything.txt"), MatchResult::None);
+    }
+
+    #[test]
+    fn escaped_hash_is_literal() {
+        let gi = matcher(&["\\#file"]);
+        assert_eq!(matches_file(&gi, "#file"), MatchResult::Ignore);
+    }
+
+    #[test]
+    fn escaped_bang_is_literal() {
+        let gi = matcher(&["\\!important"]);
+        assert_eq!(matches_file(&gi, "!important"), MatchResult::Ignore);
+    }
+
+    #[test]
+    fn trailing_spaces_are_ignored() {
+        // Gitignore spec: trailing spaces are ignored unless escaped with backslash
+        let gi = matcher(&["*.log   "]);
+        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "debug.log   "), MatchResult::None);
+    }
+
+    #[test]
+    fn match_deeply_nested_path() {
+        let gi = matcher(&["*.log"]);
+        assert_eq!(
+            matches_file(&gi, "a/b/c/d/e/f/g/deep.log"),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            matches_file(&gi, "a/b/c/d/e/f/g/deep.txt"),
+            MatchResult::None
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // match_path — common real-world patterns
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn node_modules_pattern() {
+        let gi = matcher(&["node_modules/"]);
+        assert_eq!(matches_dir(&gi, "node_modules"), MatchResult::Ignore);
+        assert_eq!(
+            matches_dir(&gi, "packages/app/node_modules"),
+            MatchResult::Ignore
+        );
+        // File named node_modules (weird but possible) should NOT match
+        assert_eq!(matches_file(&gi, "node_modules"), MatchResult::None);
+    }
+
+    #[test]
+    fn dotfile_pattern() {
+        let gi = matcher(&[".*"]);
+        assert_eq!(matches_file(&gi, ".gitignore"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, ".env"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "visible.txt"), MatchResult::None);
+    }
+
+    #[test]
+    fn complex_gitignore() {
+        let gi = matcher(&[
+            "# Build outputs",
+            "build/",
+            "dist/",
+            "*.o",
+            "*.a",
+            "",
+            "# Logs",
+            "*.log",
+            "logs/",
+            "",
+            "# Dependencies",
+            "node_modules/",
+            "vendor/",
+            "",
+            "# Keep important files",
+            "!.gitkeep",
+            "!README.md",
+        ]);
+
+        assert_eq!(matches_dir(&gi, "build"), MatchResult::Ignore);
+        assert_eq!(matches_dir(&gi, "dist"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "main.o"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "lib.a"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "app.log"), MatchResult::Ignore);
+        assert_eq!(matches_dir(&gi, "node_modules"), MatchResult::Ignore);
+        assert_eq!(matches_dir(&gi, "vendor"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi, "src/main.rs"), MatchResult::None);
+        assert_eq!(matches_file(&gi, "README.md"), MatchResult::Whitelist);
     }
 
     // -----------------------------------------------------------------------
-    // build_matcher
+    // filter_paths — batch filtering
     // -----------------------------------------------------------------------
 
     #[test]
-    fn build_empty_patterns() {
-        let gi = build_matcher(b"").expect("empty patterns should compile");
-        assert!(gi.is_empty());
+    fn filter_basic() {
+        let gi = matcher(&["*.log", "build/"]);
+        let result = batch(
+            &gi,
+            &[
+                "src/main.go",
+                "debug.log",
+                "error.log",
+                "build/",
+                "README.md",
+            ],
+        );
+        assert_eq!(result, vec!["src/main.go", "README.md"]);
     }
 
     #[test]
-    fn build_single_pattern() {
-        let gi = build_matcher(b"*.log").expect("should compile");
-        assert_eq!(gi.num_ignores(), 1);
+    fn filter_with_negation() {
+        let gi = matcher(&["*.log", "!important.log"]);
+        let result = batch(
+            &gi,
+            &["debug.log", "important.log", "error.log", "src/main.go"],
+        );
+        assert_eq!(result, vec!["important.log", "src/main.go"]);
+    }
+
+    #[test]
+    fn filter_all_ignored() {
+        let gi = matcher(&["*"]);
+        let result = batch(&gi, &["a.txt", "b.txt", "c.txt"]);
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn filter_none_ignored() {
+        let gi = matcher(&["*.log"]);
+        let result = batch(&gi, &["a.txt", "b.rs", "c.go"]);
+        assert_eq!(result, vec!["a.txt", "b.rs", "c.go"]);
+    }
+
+    #[test]
+    fn filter_empty_input() {
+        let gi = matcher(&["*.log"]);
+        let result = batch(&gi, &[]);
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn filter_preserves_order() {
+        let gi = matcher(&["*.log"]);
+        let result = batch(&gi, &["z.txt", "a.txt", "m.txt", "debug.log", "b.txt"]);
+        assert_eq!(result, vec!["z.txt", "a.txt", "m.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn filter_directory_detection_via_trailing_slash() {
+        // "build/" pattern only matches directories.
+        // In batch_filter, entries ending with "/" are treated as directories.
+        let gi = matcher(&["build/"]);
+        let result = batch(
+            &gi,
+            &[
+                "build/", // directory → should be ignored
+                "build",  // file → should NOT be ignored
+                "src/main.go",
+            ],
+        );
+        assert_eq!(result, vec!["build", "src/main.go"]);
+    }
+
+    #[test]
+    fn filter_skips_empty_lines() {
+        let gi = matcher(&["*.log"]);
+        // Simulate empty lines in the input (would appear as "" between newlines)
+        let input = "a.txt\n\nb.log\n\nc.txt\n";
+        let result: Vec<&str> = filter_paths(&Matcher::Single(gi), input);
+        assert_eq!(result, vec!["a.txt", "c.txt"]);
+    }
+
+    #[test]
+    fn filter_large_pattern_set() {
+        // Simulate a realistic .gitignore with many patterns
+        let patterns: Vec<&str> = vec![
+            "*.o",
+            "*.a",
+            "*.so",
+            "*.dylib",
+            "*.dll",
+            "*.exe",
+            "*.log",
+            "*.tmp",
+            "*.swp",
+            "*.swo",
+            "*.bak",
+            "*.orig",
+            "build/",
+            "dist/",
+            "target/",
+            "out/",
+            "node_modules/",
+            "vendor/",
+            ".git/",
+            ".DS_Store",
+            "Thumbs.db",
+            "*.pyc",
+            "__pycache__/",
+        ];
+        let gi = matcher(&patterns);
+
+        let paths = vec![
+            "src/main.rs",
+            "src/lib.rs",
+            "Cargo.toml",
+            "README.md",
+            "build/",
+            "target/",
+            "main.o",
+            "libfoo.a",
+            "libbar.so",
+            "node_modules/",
+            ".DS_Store",
+            "Thumbs.db",
+            "app.log",
+            "temp.tmp",
+            ".vim.swp",
+            "src/utils.rs",
+            "docs/guide.md",
+            "tests/test_main.rs",
+        ];
+
+        let result = batch(&gi, &paths);
+        assert_eq!(
+            result,
+            vec![
+                "src/main.rs",
+                "src/lib.rs",
+                "Cargo.toml",
+                "README.md",
+                "src/utils.rs",
+                "docs/guide.md",
+                "tests/test_main.rs",
+            ]
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // Multiple matchers coexisting
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn multiple_matchers_independent() {
+        let gi1 = matcher(&["*.log"]);
+        let gi2 = matcher(&["*.txt"]);
+
+        assert_eq!(matches_file(&gi1, "debug.log"), MatchResult::Ignore);
+        assert_eq!(matches_file(&gi1, "readme.txt"), MatchResult::None);
+
+        assert_eq!(matches_file(&gi2, "debug.log"), MatchResult::None);
+        assert_eq!(matches_file(&gi2, "readme.txt"), MatchResult::Ignore);
+    }
+
+    // -----------------------------------------------------------------------
+    // Global state (matchers HashMap) — tested via the thin layer just
+    // above the FFI boundary that we can call safely in tests.
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn store_and_retrieve_matcher() {
+        let gi = build_matcher(b"*.log").unwrap();
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        matchers().insert(id, Matcher::Single(gi));
+
+        let retrieved = matchers().get(&id).expect("matcher should exist");
+        assert_eq!(
+            match_path_any(retrieved, "debug.log", false),
+            MatchResult::Ignore
+        );
+
+        matchers().remove(&id);
+        assert!(matchers().get(&id).is_none());
+    }
+
+    #[test]
+    fn destroy_nonexistent_handle_is_noop() {
+        // Shouldn't panic or corrupt state
+        let before = matchers().len();
+        matchers().remove(&999999);
+        assert_eq!(matchers().len(), before);
+    }
+
+    // -----------------------------------------------------------------------
+    // MatchResult enum value mapping
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn match_result_integer_values() {
+        // Verify the discriminant values match what the Go side expects
+        assert_eq!(MatchResult::None as i32, 0);
+        assert_eq!(MatchResult::Ignore as i32, 1);
+        assert_eq!(MatchResult::Whitelist as i32, 2);
+    }
+
+    // -----------------------------------------------------------------------
+    // Parent-directory matching — matched_path_or_any_parents propagation
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn parent_match_target_dir_ignores_children() {
+        let gi = matcher(&["target/"]);
+        // The directory itself is ignored
+        assert_eq!(matches_dir(&gi, "target"), MatchResult::Ignore);
+        // Children of an ignored directory are also ignored
+        assert_eq!(matches_file(&gi, "target/foo/bar.rs"), MatchResult::Ignore);
+        assert_eq!(
+            matches_file(&gi, "target/debug/build/output"),
+            MatchResult::Ignore
+        );
+    }
+
+    #[test]
+    fn parent_match_node_modules_ignores_children() {
+        let gi = matcher(&["node_modules/"]);
+        assert_eq!(
+            matches_file(&gi, "node_modules/express/index.js"),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            matches_file(&gi, "node_modules/.package-lock.json"),
+            MatchResult::Ignore
+        );
+        // Nested node_modules children too
+        assert_eq!(
+            matches_dir(&gi, "packages/app/node_modules"),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            matches_file(&gi, "packages/app/node_modules/lodash/index.js"),
+            MatchResult::Ignore
+        );
+    }
+
+    #[test]
+    fn parent_match_batch_filter_children_of_ignored_dir() {
+        let gi = matcher(&["build/"]);
+        let result = batch(
+            &gi,
+            &[
+                "src/main.rs",
+                "build/",
+                "build/output.o",
+                "build/lib/foo.a",
+                "README.md",
+            ],
+        );
+        assert_eq!(result, vec!["src/main.rs", "README.md"]);
     }
 
     #[test]
-    fn build_multiple_patterns() {
-        let gi = build_matcher(b"*.log\nbuild/\ntemp*").expect("should compile");
-        assert_eq!(gi.num_ignores(), 3);
+    fn parent_match_negation_can_whitelist_child() {
+        // A negation pattern can re-include a specific file under an ignored
+        // directory when using matched_path_or_any_parents.
+        let gi = matcher(&["build/", "!build/important.txt"]);
+        // The directory itself is ignored
+        assert_eq!(matches_dir(&gi, "build"), MatchResult::Ignore);
+        // The negation pattern whitelists this specific child
+        assert_eq!(
+            matches_file(&gi, "build/important.txt"),
+            MatchResult::Whitelist
+        );
+        // Other children are still ignored
+        assert_eq!(matches_file(&gi, "build/output.o"), MatchResult::Ignore);
     }
 
-    #[test]
-    fn build_with_comments_and_blanks() {
-        let gi = build_matcher(b"# this is a comment\n\n*.log\n\n# another comment\nbuild/")
-            .expect("should compile");
-        assert_eq!(gi.num_ignores(), 2);
+    // -----------------------------------------------------------------------
+    // Layered matcher — closest-ancestor-wins across directory depths
+    // -----------------------------------------------------------------------
+
+    fn layered(bases_and_patterns: &[(&str, &str)]) -> Vec<(PathBuf, Gitignore)> {
+        let mut layers: Vec<(PathBuf, Gitignore)> = bases_and_patterns
+            .iter()
+            .map(|(base, patterns)| (PathBuf::from(base), matcher(&[patterns])))
+            .collect();
+        layers.sort_by_key(|(base, _)| std::cmp::Reverse(base.components().count()));
+        layers
     }
 
     #[test]
-    fn build_with_negation() {
-        let gi = build_matcher(b"*.log\n!important.log").expect("should compile");
-        assert_eq!(gi.num_ignores(), 1);
-        assert_eq!(gi.num_whitelists(), 1);
+    fn layered_deeper_gitignore_overrides_shallower() {
+        // Root ignores all logs; a nested dir re-includes its own log.
+        let layers = layered(&[("/", "*.log"), ("/src", "!debug.log")]);
+        assert_eq!(
+            match_path_layered(&layers, "/app.log", false),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            match_path_layered(&layers, "/src/debug.log", false),
+            MatchResult::Whitelist
+        );
+        assert_eq!(
+            match_path_layered(&layers, "/src/other.log", false),
+            MatchResult::Ignore
+        );
     }
 
-    // -----------------------------------------------------------------------
-    // match_path — basic globs
-    // -----------------------------------------------------------------------
-
     #[test]
-    fn match_star_extension() {
-        let gi = matcher(&["*.log"]);
-        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "error.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "app.txt"), MatchResult::None);
-        assert_eq!(matches_file(&gi, "src/debug.log"), MatchResult::Ignore);
+    fn layered_none_falls_through_to_shallower_layer() {
+        // A deeper layer that has no opinion on a path falls through to the
+        // next shallower ancestor rather than returning None outright.
+        let layers = layered(&[("/", "*.log"), ("/src", "*.tmp")]);
+        assert_eq!(
+            match_path_layered(&layers, "/src/debug.log", false),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            match_path_layered(&layers, "/src/scratch.tmp", false),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            match_path_layered(&layers, "/src/main.rs", false),
+            MatchResult::None
+        );
     }
 
     #[test]
-    fn match_exact_filename() {
-        let gi = matcher(&["Thumbs.db"]);
-        assert_eq!(matches_file(&gi, "Thumbs.db"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "src/Thumbs.db"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "thumbs.db"), MatchResult::None);
+    fn layered_unrelated_subtree_unaffected() {
+        let layers = layered(&[("/", "*.log"), ("/vendor", "!keep.log")]);
+        // "/other/keep.log" is not under "/vendor", so only the root layer applies.
+        assert_eq!(
+            match_path_layered(&layers, "/other/keep.log", false),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            match_path_layered(&layers, "/vendor/keep.log", false),
+            MatchResult::Whitelist
+        );
     }
 
     #[test]
-    fn match_prefix_star() {
-        let gi = matcher(&["temp*"]);
-        assert_eq!(matches_file(&gi, "tempfile"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "temporary.txt"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "atemp"), MatchResult::None);
+    fn layered_no_matching_layer_returns_none() {
+        let layers = layered(&[("/src", "*.log")]);
+        assert_eq!(
+            match_path_layered(&layers, "/docs/readme.md", false),
+            MatchResult::None
+        );
     }
 
     #[test]
-    fn match_double_star() {
-        let gi = matcher(&["**/logs"]);
-        assert_eq!(matches_file(&gi, "logs"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "a/logs"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "a/b/logs"), MatchResult::Ignore);
+    fn layered_is_sorted_deepest_base_first() {
+        let layers = layered(&[("/", "*.log"), ("/a/b/c", "*.tmp"), ("/a", "*.bak")]);
+        assert_eq!(layers[0].0, PathBuf::from("/a/b/c"));
+        assert_eq!(layers[1].0, PathBuf::from("/a"));
+        assert_eq!(layers[2].0, PathBuf::from("/"));
     }
 
     #[test]
-    fn match_double_star_with_extension() {
-        let gi = matcher(&["**/*.log"]);
-        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "a/debug.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "a/b/c/debug.log"), MatchResult::Ignore);
+    fn read_i32_and_read_bytes_roundtrip() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&5i32.to_le_bytes());
+        buf.extend_from_slice(b"hello");
+
+        let mut offset = 0;
+        assert_eq!(read_i32(&buf, &mut offset), Some(5));
+        assert_eq!(read_bytes(&buf, &mut offset, 5), Some(b"hello".as_slice()));
+        assert_eq!(offset, buf.len());
     }
 
     #[test]
-    fn match_question_mark() {
-        let gi = matcher(&["debug?.log"]);
-        assert_eq!(matches_file(&gi, "debug0.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "debugA.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::None);
-        assert_eq!(matches_file(&gi, "debug10.log"), MatchResult::None);
+    fn read_i32_rejects_truncated_buffer() {
+        let buf = [0u8, 1, 2];
+        let mut offset = 0;
+        assert_eq!(read_i32(&buf, &mut offset), None);
     }
 
     #[test]
-    fn match_character_class() {
-        let gi = matcher(&["debug[0-9].log"]);
-        assert_eq!(matches_file(&gi, "debug0.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "debug9.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "debugA.log"), MatchResult::None);
+    fn read_bytes_rejects_out_of_range_length() {
+        let buf = b"abc";
+        let mut offset = 0;
+        assert_eq!(read_bytes(buf, &mut offset, 10), None);
     }
 
     // -----------------------------------------------------------------------
-    // match_path — directory patterns
+    // Override matcher — include/exclude globs
     // -----------------------------------------------------------------------
 
-    #[test]
-    fn match_directory_trailing_slash_pattern() {
-        // Pattern "build/" should only match directories, not files named "build"
-        let gi = matcher(&["build/"]);
-        assert_eq!(matches_dir(&gi, "build"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "build"), MatchResult::None);
-        assert_eq!(matches_dir(&gi, "src/build"), MatchResult::Ignore);
+    fn overrides(globs: &[&str]) -> Override {
+        build_overrides(globs.join("\n").as_bytes()).expect("globs should compile")
     }
 
-    #[test]
-    fn match_directory_without_trailing_slash_pattern() {
-        // Pattern "build" without trailing slash matches both files and dirs
-        let gi = matcher(&["build"]);
-        assert_eq!(matches_file(&gi, "build"), MatchResult::Ignore);
-        assert_eq!(matches_dir(&gi, "build"), MatchResult::Ignore);
+    fn matches_override_file(ov: &Override, path: &str) -> MatchResult {
+        match_path_override(ov, path, false)
     }
 
-    #[test]
-    fn match_nested_directory_pattern() {
-        let gi = matcher(&["logs/**/debug.log"]);
-        assert_eq!(matches_file(&gi, "logs/debug.log"), MatchResult::Ignore);
-        assert_eq!(
-            matches_file(&gi, "logs/monday/debug.log"),
-            MatchResult::Ignore
-        );
-        assert_eq!(
-            matches_file(&gi, "logs/monday/pm/debug.log"),
-            MatchResult::Ignore
-        );
+    fn matches_override_dir(ov: &Override, path: &str) -> MatchResult {
+        match_path_override(ov, path, true)
     }
 
-    // -----------------------------------------------------------------------
-    // match_path — negation patterns
-    // -----------------------------------------------------------------------
-
     #[test]
-    fn negation_basic() {
-        let gi = matcher(&["*.log", "!important.log"]);
-        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "error.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "important.log"), MatchResult::Whitelist);
+    fn override_whitelist_glob_includes_matching_file() {
+        let ov = overrides(&["*.rs"]);
+        assert_eq!(matches_override_file(&ov, "main.rs"), MatchResult::Whitelist);
     }
 
     #[test]
-    fn negation_order_matters() {
-        // In gitignore, later patterns override earlier ones
-        let gi = matcher(&["*.log", "!important.log", "important.log"]);
-        // The last pattern re-ignores important.log
-        assert_eq!(matches_file(&gi, "important.log"), MatchResult::Ignore);
+    fn override_unmatched_with_whitelist_present_is_ignored() {
+        // With at least one whitelist glob, anything that doesn't match is ignored.
+        let ov = overrides(&["*.rs", "*.toml"]);
+        assert_eq!(matches_override_file(&ov, "README.md"), MatchResult::Ignore);
     }
 
     #[test]
-    fn negation_of_directory() {
-        let gi = matcher(&["build/", "!build/"]);
-        assert_eq!(matches_dir(&gi, "build"), MatchResult::Whitelist);
+    fn override_unmatched_without_whitelist_is_none() {
+        // With only ignore (`!`) globs and no whitelist, unmatched is None.
+        let ov = overrides(&["!*.log"]);
+        assert_eq!(matches_override_file(&ov, "README.md"), MatchResult::None);
     }
 
-    // -----------------------------------------------------------------------
-    // match_path — rooted / anchored patterns
-    // -----------------------------------------------------------------------
+    #[test]
+    fn override_bang_prefix_is_an_ignore_rule() {
+        let ov = overrides(&["*.rs", "!generated.rs"]);
+        assert_eq!(matches_override_file(&ov, "main.rs"), MatchResult::Whitelist);
+        assert_eq!(
+            matches_override_file(&ov, "generated.rs"),
+            MatchResult::Ignore
+        );
+    }
 
     #[test]
-    fn rooted_pattern_with_leading_slash() {
-        // A leading slash anchors the pattern to the root
-        let gi = matcher(&["/build"]);
-        assert_eq!(matches_file(&gi, "build"), MatchResult::Ignore);
-        // Should NOT match in subdirectories
-        assert_eq!(matches_file(&gi, "src/build"), MatchResult::None);
+    fn override_unmatched_directory_with_whitelist_present_is_none() {
+        // Directories must still be descended into to find included files
+        // within them, so the unmatched-ignore case only applies to files.
+        let ov = overrides(&["*.rs"]);
+        assert_eq!(matches_override_dir(&ov, "src"), MatchResult::None);
     }
 
     #[test]
-    fn pattern_with_middle_slash_is_anchored() {
-        // A pattern containing a slash (other than trailing) is anchored
-        let gi = matcher(&["doc/frotz"]);
-        assert_eq!(matches_file(&gi, "doc/frotz"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "a/doc/frotz"), MatchResult::None);
+    fn override_empty_set_matches_nothing() {
+        let ov = overrides(&[]);
+        assert_eq!(matches_override_file(&ov, "anything.txt"), MatchResult::None);
     }
 
     // -----------------------------------------------------------------------
-    // match_path — edge cases
+    // Composite matcher — trie-backed incremental layering
     // -----------------------------------------------------------------------
 
-    #[test]
-    fn empty_matcher_matches_nothing() {
-        let gi = matcher(&[]);
-        assert_eq!(matches_file(&gi, "anything.txt"), MatchResult::None);
-        assert_eq!(matches_dir(&gi, "anydir"), MatchResult::None);
+    fn composite(sources: &[(&str, &str)]) -> Composite {
+        let mut c = Composite::new();
+        for (origin, patterns) in sources {
+            c.add_source(PathBuf::from(*origin), matcher(&[patterns]));
+        }
+        c
     }
 
     #[test]
-    fn comments_only_matcher_matches_nothing() {
-        let gi = matcher(&["# just a comment", "# another comment"]);
-        assert_eq!(matches_file(&gi, "anything.txt"), MatchResult::None);
+    fn composite_empty_matches_nothing() {
+        let c = Composite::new();
+        assert_eq!(
+            match_path_composite(&c, "/anything.txt", false),
+            MatchResult::None
+        );
     }
 
     #[test]
-    fn escaped_hash_is_literal() {
-        let gi = matcher(&["\\#file"]);
-        assert_eq!(matches_file(&gi, "#file"), MatchResult::Ignore);
+    fn composite_deeper_source_overrides_shallower() {
+        let c = composite(&[("/", "*.log"), ("/src", "!debug.log")]);
+        assert_eq!(
+            match_path_composite(&c, "/app.log", false),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            match_path_composite(&c, "/src/debug.log", false),
+            MatchResult::Whitelist
+        );
     }
 
     #[test]
-    fn escaped_bang_is_literal() {
-        let gi = matcher(&["\\!important"]);
-        assert_eq!(matches_file(&gi, "!important"), MatchResult::Ignore);
+    fn composite_none_falls_through_to_shallower_source() {
+        let c = composite(&[("/", "*.log"), ("/src", "*.tmp")]);
+        assert_eq!(
+            match_path_composite(&c, "/src/debug.log", false),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            match_path_composite(&c, "/src/main.rs", false),
+            MatchResult::None
+        );
     }
 
     #[test]
-    fn trailing_spaces_are_ignored() {
-        // Gitignore spec: trailing spaces are ignored unless escaped with backslash
-        let gi = matcher(&["*.log   "]);
-        assert_eq!(matches_file(&gi, "debug.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "debug.log   "), MatchResult::None);
+    fn composite_sources_for_orders_deepest_first() {
+        let c = composite(&[("/", "*.log"), ("/a/b", "*.tmp"), ("/a", "*.bak")]);
+        let chain = c.sources_for(Path::new("/a/b/c.txt"));
+        let origins: Vec<&Path> = chain.iter().map(|(origin, _)| origin.as_path()).collect();
+        assert_eq!(
+            origins,
+            vec![Path::new("/a/b"), Path::new("/a"), Path::new("/")]
+        );
     }
 
     #[test]
-    fn match_deeply_nested_path() {
-        let gi = matcher(&["*.log"]);
+    fn composite_unrelated_subtree_unaffected() {
+        let c = composite(&[("/", "*.log"), ("/vendor", "!keep.log")]);
         assert_eq!(
-            matches_file(&gi, "a/b/c/d/e/f/g/deep.log"),
+            match_path_composite(&c, "/other/keep.log", false),
             MatchResult::Ignore
         );
         assert_eq!(
-            matches_file(&gi, "a/b/c/d/e/f/g/deep.txt"),
-            MatchResult::None
+            match_path_composite(&c, "/vendor/keep.log", false),
+            MatchResult::Whitelist
         );
     }
 
     // -----------------------------------------------------------------------
-    // match_path — common real-world patterns
+    // Types matcher — built-in and custom file type registry
     // -----------------------------------------------------------------------
 
-    #[test]
-    fn node_modules_pattern() {
-        let gi = matcher(&["node_modules/"]);
-        assert_eq!(matches_dir(&gi, "node_modules"), MatchResult::Ignore);
-        assert_eq!(
-            matches_dir(&gi, "packages/app/node_modules"),
-            MatchResult::Ignore
-        );
-        // File named node_modules (weird but possible) should NOT match
-        assert_eq!(matches_file(&gi, "node_modules"), MatchResult::None);
-    }
-
-    #[test]
-    fn dotfile_pattern() {
-        let gi = matcher(&[".*"]);
-        assert_eq!(matches_file(&gi, ".gitignore"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, ".env"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "visible.txt"), MatchResult::None);
+    fn types_matcher(selected: &[&str], negated: &[&str]) -> Types {
+        let mut builder = types_builder_with_defaults();
+        for name in selected {
+            builder.select(name);
+        }
+        for name in negated {
+            builder.negate(name);
+        }
+        builder.build().expect("types should build")
     }
 
     #[test]
-    fn complex_gitignore() {
-        let gi = matcher(&[
-            "# Build outputs",
-            "build/",
-            "dist/",
-            "*.o",
-            "*.a",
-            "",
-            "# Logs",
-            "*.log",
-            "logs/",
-            "",
-            "# Dependencies",
-            "node_modules/",
-            "vendor/",
-            "",
-            "# Keep important files",
-            "!.gitkeep",
-            "!README.md",
-        ]);
-
-        assert_eq!(matches_dir(&gi, "build"), MatchResult::Ignore);
-        assert_eq!(matches_dir(&gi, "dist"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "main.o"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "lib.a"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "app.log"), MatchResult::Ignore);
-        assert_eq!(matches_dir(&gi, "node_modules"), MatchResult::Ignore);
-        assert_eq!(matches_dir(&gi, "vendor"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi, "src/main.rs"), MatchResult::None);
-        assert_eq!(matches_file(&gi, "README.md"), MatchResult::Whitelist);
+    fn types_selects_only_named_type() {
+        let types = types_matcher(&["rust"], &[]);
+        assert_eq!(
+            match_path_types(&types, "main.rs", false),
+            MatchResult::Whitelist
+        );
+        assert_eq!(
+            match_path_types(&types, "script.py", false),
+            MatchResult::Ignore
+        );
     }
 
-    // -----------------------------------------------------------------------
-    // filter_paths — batch filtering
-    // -----------------------------------------------------------------------
+    #[test]
+    fn types_negate_excludes_named_type() {
+        let types = types_matcher(&[], &["py"]);
+        assert_eq!(
+            match_path_types(&types, "script.py", false),
+            MatchResult::Ignore
+        );
+        assert_eq!(
+            match_path_types(&types, "main.rs", false),
+            MatchResult::None
+        );
+    }
 
     #[test]
-    fn filter_basic() {
-        let gi = matcher(&["*.log", "build/"]);
-        let result = batch(
-            &gi,
-            &[
-                "src/main.go",
-                "debug.log",
-                "error.log",
-                "build/",
-                "README.md",
-            ],
+    fn types_directories_never_match() {
+        let types = types_matcher(&["rust"], &[]);
+        assert_eq!(match_path_types(&types, "src", true), MatchResult::None);
+    }
+
+    #[test]
+    fn types_no_selection_matches_everything_as_none() {
+        let types = types_builder_with_defaults()
+            .build()
+            .expect("types should build");
+        assert_eq!(
+            match_path_types(&types, "main.rs", false),
+            MatchResult::None
         );
-        assert_eq!(result, vec!["src/main.go", "README.md"]);
     }
 
     #[test]
-    fn filter_with_negation() {
-        let gi = matcher(&["*.log", "!important.log"]);
-        let result = batch(
-            &gi,
-            &["debug.log", "important.log", "error.log", "src/main.go"],
+    fn custom_type_registered_before_build_is_selectable() {
+        custom_types().push(("widget".to_string(), "*.widget".to_string()));
+        let types = types_matcher(&["widget"], &[]);
+        assert_eq!(
+            match_path_types(&types, "thing.widget", false),
+            MatchResult::Whitelist
         );
-        assert_eq!(result, vec!["important.log", "src/main.go"]);
     }
 
     #[test]
-    fn filter_all_ignored() {
-        let gi = matcher(&["*"]);
-        let result = batch(&gi, &["a.txt", "b.txt", "c.txt"]);
-        assert!(result.is_empty());
+    fn register_custom_type_rejects_glob_that_fails_to_compile() {
+        let before = custom_types().len();
+        let result = register_custom_type_defs("broken", &["[z-a]"]);
+        assert!(matches!(result, Err(-4)));
+        assert_eq!(custom_types().len(), before, "a rejected glob must not be stored");
     }
 
     #[test]
-    fn filter_none_ignored() {
-        let gi = matcher(&["*.log"]);
-        let result = batch(&gi, &["a.txt", "b.rs", "c.go"]);
-        assert_eq!(result, vec!["a.txt", "b.rs", "c.go"]);
+    fn list_type_names_includes_rust() {
+        let names: Vec<String> = types_builder_with_defaults()
+            .definitions()
+            .iter()
+            .map(|def| def.name().to_string())
+            .collect();
+        assert!(names.iter().any(|n| n == "rust"));
     }
 
     #[test]
-    fn filter_empty_input() {
-        let gi = matcher(&["*.log"]);
-        let result = batch(&gi, &[]);
-        assert!(result.is_empty());
+    fn parse_names_skips_empty_lines() {
+        let names = parse_names(b"rust\n\npy\n").expect("valid utf8");
+        assert_eq!(names, vec!["rust", "py"]);
     }
 
+    // -----------------------------------------------------------------------
+    // explain_* — diagnostic reporting of the deciding glob/source
+    // -----------------------------------------------------------------------
+
     #[test]
-    fn filter_preserves_order() {
+    fn explain_single_reports_matched_glob() {
         let gi = matcher(&["*.log"]);
-        let result = batch(&gi, &["z.txt", "a.txt", "m.txt", "debug.log", "b.txt"]);
-        assert_eq!(result, vec!["z.txt", "a.txt", "m.txt", "b.txt"]);
+        let explanation = explain_single(&gi, "debug.log", false);
+        assert_eq!(explanation.result, MatchResult::Ignore);
+        assert_eq!(explanation.glob.as_deref(), Some("*.log"));
+        assert_eq!(explanation.source, None);
     }
 
     #[test]
-    fn filter_directory_detection_via_trailing_slash() {
-        // "build/" pattern only matches directories.
-        // In batch_filter, entries ending with "/" are treated as directories.
-        let gi = matcher(&["build/"]);
-        let result = batch(
-            &gi,
-            &[
-                "build/", // directory → should be ignored
-                "build",  // file → should NOT be ignored
-                "src/main.go",
-            ],
-        );
-        assert_eq!(result, vec!["build", "src/main.go"]);
+    fn explain_single_none_has_no_glob() {
+        let gi = matcher(&["*.log"]);
+        let explanation = explain_single(&gi, "main.rs", false);
+        assert_eq!(explanation.result, MatchResult::None);
+        assert_eq!(explanation.glob, None);
     }
 
     #[test]
-    fn filter_skips_empty_lines() {
-        let gi = matcher(&["*.log"]);
-        // Simulate empty lines in the input (would appear as "" between newlines)
-        let input = "a.txt\n\nb.log\n\nc.txt\n";
-        let result: Vec<&str> = filter_paths(&gi, input);
-        assert_eq!(result, vec!["a.txt", "c.txt"]);
+    fn explain_layered_reports_deciding_layer_origin() {
+        let layers = layered(&[("/", "*.log"), ("/src", "!debug.log")]);
+        let explanation = explain_layered(&layers, "/src/debug.log", false);
+        assert_eq!(explanation.result, MatchResult::Whitelist);
+        assert_eq!(explanation.glob.as_deref(), Some("!debug.log"));
+        assert_eq!(explanation.source.as_deref(), Some("/src"));
     }
 
     #[test]
-    fn filter_large_pattern_set() {
-        // Simulate a realistic .gitignore with many patterns
-        let patterns: Vec<&str> = vec![
-            "*.o",
-            "*.a",
-            "*.so",
-            "*.dylib",
-            "*.dll",
-            "*.exe",
-            "*.log",
-            "*.tmp",
-            "*.swp",
-            "*.swo",
-            "*.bak",
-            "*.orig",
-            "build/",
-            "dist/",
-            "target/",
-            "out/",
-            "node_modules/",
-            "vendor/",
-            ".git/",
-            ".DS_Store",
-            "Thumbs.db",
-            "*.pyc",
-            "__pycache__/",
-        ];
-        let gi = matcher(&patterns);
+    fn explain_composite_reports_deciding_source_origin() {
+        let c = composite(&[("/", "*.log"), ("/vendor", "!keep.log")]);
+        let explanation = explain_composite(&c, "/vendor/keep.log", false);
+        assert_eq!(explanation.result, MatchResult::Whitelist);
+        assert_eq!(explanation.glob.as_deref(), Some("!keep.log"));
+        assert_eq!(explanation.source.as_deref(), Some("/vendor"));
+    }
 
-        let paths = vec![
-            "src/main.rs",
-            "src/lib.rs",
-            "Cargo.toml",
-            "README.md",
-            "build/",
-            "target/",
-            "main.o",
-            "libfoo.a",
-            "libbar.so",
-            "node_modules/",
-            ".DS_Store",
-            "Thumbs.db",
-            "app.log",
-            "temp.tmp",
-            ".vim.swp",
-            "src/utils.rs",
-            "docs/guide.md",
-            "tests/test_main.rs",
-        ];
+    #[test]
+    fn explain_overrides_has_result_but_no_glob() {
+        let ov = overrides(&["*.rs"]);
+        let explanation = explain_overrides(&ov, "README.md", false);
+        assert_eq!(explanation.result, MatchResult::Ignore);
+        assert_eq!(explanation.glob, None);
+    }
 
-        let result = batch(&gi, &paths);
-        assert_eq!(
-            result,
-            vec![
-                "src/main.rs",
-                "src/lib.rs",
-                "Cargo.toml",
-                "README.md",
-                "src/utils.rs",
-                "docs/guide.md",
-                "tests/test_main.rs",
-            ]
-        );
+    #[test]
+    fn explain_types_reports_matched_type_name() {
+        let types = types_matcher(&["rust"], &[]);
+        let explanation = explain_types(&types, "main.rs", false);
+        assert_eq!(explanation.result, MatchResult::Whitelist);
+        assert_eq!(explanation.glob.as_deref(), Some("rust"));
     }
 
     // -----------------------------------------------------------------------
-    // Multiple matchers coexisting
+    // should_descend — directory-boundary pruning for a host-side walker
     // -----------------------------------------------------------------------
 
+    fn should_descend_dir(matcher: &Matcher, path: &str) -> bool {
+        !matches!(match_path_any(matcher, path, true), MatchResult::Ignore)
+    }
+
     #[test]
-    fn multiple_matchers_independent() {
-        let gi1 = matcher(&["*.log"]);
-        let gi2 = matcher(&["*.txt"]);
+    fn should_descend_prunes_ignored_directory() {
+        let m = Matcher::Single(matcher(&["node_modules/"]));
+        assert!(!should_descend_dir(&m, "node_modules"));
+        assert!(!should_descend_dir(&m, "packages/app/node_modules"));
+    }
 
-        assert_eq!(matches_file(&gi1, "debug.log"), MatchResult::Ignore);
-        assert_eq!(matches_file(&gi1, "readme.txt"), MatchResult::None);
+    #[test]
+    fn should_descend_allows_unignored_directory() {
+        let m = Matcher::Single(matcher(&["node_modules/"]));
+        assert!(should_descend_dir(&m, "src"));
+    }
 
-        assert_eq!(matches_file(&gi2, "debug.log"), MatchResult::None);
-        assert_eq!(matches_file(&gi2, "readme.txt"), MatchResult::Ignore);
+    #[test]
+    fn should_descend_allows_whitelisted_directory() {
+        let m = Matcher::Single(matcher(&["build/", "!build/"]));
+        assert!(should_descend_dir(&m, "build"));
     }
 
     // -----------------------------------------------------------------------
-    // Global state (matchers HashMap) — tested via the thin layer just
-    // above the FFI boundary that we can call safely in tests.
+    // build_matcher_from_sources — one-call composite construction
     // -----------------------------------------------------------------------
 
+    fn sources_buffer(sources: &[(&str, &str)]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for (origin, patterns) in sources {
+            buf.extend_from_slice(&(origin.len() as i32).to_le_bytes());
+            buf.extend_from_slice(origin.as_bytes());
+            buf.extend_from_slice(&(patterns.len() as i32).to_le_bytes());
+            buf.extend_from_slice(patterns.as_bytes());
+        }
+        buf
+    }
+
+    fn build_from_sources(sources: &[(&str, &str)]) -> Result<Composite, i32> {
+        let buf = sources_buffer(sources);
+        composite_from_buffer(&buf, sources.len() as i32)
+    }
+
     #[test]
-    fn store_and_retrieve_matcher() {
-        let gi = build_matcher(b"*.log").unwrap();
-        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
-        matchers().insert(id, gi);
+    fn build_from_sources_empty_matches_nothing() {
+        let c = build_from_sources(&[]).unwrap();
+        assert_eq!(
+            match_path_composite(&c, "/anything.txt", false),
+            MatchResult::None
+        );
+    }
 
-        let retrieved = matchers().get(&id).expect("matcher should exist");
+    #[test]
+    fn build_from_sources_deeper_source_overrides_shallower() {
+        let c = build_from_sources(&[("/", "*.log"), ("/src", "!debug.log")]).unwrap();
         assert_eq!(
-            match_path(retrieved, "debug.log", false),
+            match_path_composite(&c, "/app.log", false),
             MatchResult::Ignore
         );
-
-        matchers().remove(&id);
-        assert!(matchers().get(&id).is_none());
+        assert_eq!(
+            match_path_composite(&c, "/src/debug.log", false),
+            MatchResult::Whitelist
+        );
     }
 
     #[test]
-    fn destroy_nonexistent_handle_is_noop() {
-        // Shouldn't panic or corrupt state
-        let before = matchers().len();
-        matchers().remove(&999999);
-        assert_eq!(matchers().len(), before);
+    fn build_from_sources_rejects_truncated_buffer() {
+        let buf: Vec<u8> = 3i32.to_le_bytes().to_vec();
+        assert!(matches!(composite_from_buffer(&buf, 1), Err(-4)));
     }
 
-    // -----------------------------------------------------------------------
-    // MatchResult enum value mapping
-    // -----------------------------------------------------------------------
-
     #[test]
-    fn match_result_integer_values() {
-        // Verify the discriminant values match what the Go side expects
-        assert_eq!(MatchResult::None as i32, 0);
-        assert_eq!(MatchResult::Ignore as i32, 1);
-        assert_eq!(MatchResult::Whitelist as i32, 2);
+    fn build_from_sources_validates_arguments_before_touching_memory() {
+        assert_eq!(build_matcher_from_sources(0, 0, -1), -1);
+        assert_eq!(build_matcher_from_sources(0, -1, 1), -2);
+        assert_eq!(build_matcher_from_sources(0, 4, 1), -3);
     }
 
     // -----------------------------------------------------------------------
-    // Parent-directory matching — matched_path_or_any_parents propagation
+    // discover_composite
+    //
+    // Touches a real filesystem via a unique directory under
+    // `std::env::temp_dir()`, standing in for the host-satisfied imports on
+    // `wasm32-unknown-unknown` (see `host_exists`/`read_host_file`'s
+    // native-target implementations).
     // -----------------------------------------------------------------------
 
-    #[test]
-    fn parent_match_target_dir_ignores_children() {
-        let gi = matcher(&["target/"]);
-        // The directory itself is ignored
-        assert_eq!(matches_dir(&gi, "target"), MatchResult::Ignore);
-        // Children of an ignored directory are also ignored
-        assert_eq!(matches_file(&gi, "target/foo/bar.rs"), MatchResult::Ignore);
-        assert_eq!(
-            matches_file(&gi, "target/debug/build/output"),
-            MatchResult::Ignore
-        );
+    fn temp_test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "go-ignore-rs-test-{}-{}-{:?}",
+            name,
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create temp test dir");
+        dir
     }
 
     #[test]
-    fn parent_match_node_modules_ignores_children() {
-        let gi = matcher(&["node_modules/"]);
+    fn discover_composite_walks_up_to_git_root_and_stops() {
+        let root = temp_test_dir("discover-stops-at-git");
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        let nested = root.join("src").join("inner");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join(".gitignore"), "!debug.log\n").unwrap();
+
+        let composite = discover_composite(&nested, true).expect("should discover");
         assert_eq!(
-            matches_file(&gi, "node_modules/express/index.js"),
+            match_path_composite(&composite, &nested.join("app.log").to_string_lossy(), false),
             MatchResult::Ignore
         );
         assert_eq!(
-            matches_file(&gi, "node_modules/.package-lock.json"),
-            MatchResult::Ignore
+            match_path_composite(&composite, &nested.join("debug.log").to_string_lossy(), false),
+            MatchResult::Whitelist
         );
-        // Nested node_modules children too
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn discover_composite_ignores_gitignore_when_disabled() {
+        let root = temp_test_dir("discover-gitignore-disabled");
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(root.join(".ignore"), "*.tmp\n").unwrap();
+
+        let composite = discover_composite(&root, false).expect("should discover");
         assert_eq!(
-            matches_dir(&gi, "packages/app/node_modules"),
-            MatchResult::Ignore
+            match_path_composite(&composite, &root.join("app.log").to_string_lossy(), false),
+            MatchResult::None
         );
         assert_eq!(
-            matches_file(&gi, "packages/app/node_modules/lodash/index.js"),
+            match_path_composite(&composite, &root.join("app.tmp").to_string_lossy(), false),
             MatchResult::Ignore
         );
-    }
 
-    #[test]
-    fn parent_match_batch_filter_children_of_ignored_dir() {
-        let gi = matcher(&["build/"]);
-        let result = batch(
-            &gi,
-            &[
-                "src/main.rs",
-                "build/",
-                "build/output.o",
-                "build/lib/foo.a",
-                "README.md",
-            ],
-        );
-        assert_eq!(result, vec!["src/main.rs", "README.md"]);
+        std::fs::remove_dir_all(&root).ok();
     }
 
+    // -----------------------------------------------------------------------
+    // walk_dir
+    //
+    // Touches a real filesystem via a unique directory under
+    // `std::env::temp_dir()`, standing in for the host-satisfied `host_list_dir`
+    // import on `wasm32-unknown-unknown`.
+    // -----------------------------------------------------------------------
+
     #[test]
-    fn parent_match_negation_can_whitelist_child() {
-        // A negation pattern can re-include a specific file under an ignored
-        // directory when using matched_path_or_any_parents.
-        let gi = matcher(&["build/", "!build/important.txt"]);
-        // The directory itself is ignored
-        assert_eq!(matches_dir(&gi, "build"), MatchResult::Ignore);
-        // The negation pattern whitelists this specific child
-        assert_eq!(
-            matches_file(&gi, "build/important.txt"),
-            MatchResult::Whitelist
-        );
-        // Other children are still ignored
-        assert_eq!(matches_file(&gi, "build/output.o"), MatchResult::Ignore);
+    fn walk_dir_prunes_ignored_directories_and_collects_survivors() {
+        let root = temp_test_dir("walk-dir-prunes");
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join("target").join("build.o"), "").unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src").join("main.rs"), "").unwrap();
+        std::fs::write(root.join("README.md"), "").unwrap();
+
+        let gi = matcher(&["target/"]);
+        let mut kept = Vec::new();
+        walk_dir(&Matcher::Single(gi), &root, &mut kept);
+
+        let root_main_rs = root.join("src").join("main.rs").to_string_lossy().into_owned();
+        let root_readme = root.join("README.md").to_string_lossy().into_owned();
+        assert!(kept.contains(&root_main_rs));
+        assert!(kept.contains(&root_readme));
+        assert!(!kept.iter().any(|p| p.contains("build.o")));
+
+        std::fs::remove_dir_all(&root).ok();
     }
 }